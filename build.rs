@@ -1,6 +1,6 @@
 use ethers::contract::Abigen;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Path to Depositor contract's JSON ABI. If it is absent use `npm run install && npm run compile`
 /// in `repository/contracts` to compile it.
@@ -8,12 +8,62 @@ const DEPOSITOR_ABI_ARTIFACTS_PATH: &str =
     "contracts/artifacts/contracts/Depositor.sol/Depositor.json";
 const DEPOSITOR_RUST_BINDINGS_PATH: &str = "src/depositor_contract.rs";
 
+/// Directory with the Circom artifacts to embed with the `embed-artifacts` feature. It can be
+/// overridden with the `ATOMIC_SWAP_ARTIFACTS_DIR` environment variable. Use
+/// `./scripts/atomic-swap-pre-build.sh` to produce it.
+const DEFAULT_ARTIFACTS_DIR: &str = "atomic_swap.dev";
+
+/// The verification key embedded with the `embed-artifacts` feature: the file name in the
+/// artifacts directory and the environment variable its path is passed to `include_bytes!` with.
+const EMBEDDED_VERIFICATION_KEY: (&str, &str) =
+    ("verification_key.json", "ATOMIC_SWAP_VERIFICATION_KEY_PATH");
+
+/// The witness calculator embedded with the `embed-artifacts` feature if the prover is enabled.
+const EMBEDDED_WITNESS_CALCULATOR: (&str, &str) = ("atomic_swap.wasm", "ATOMIC_SWAP_WASM_PATH");
+
+/// The proving key embedded with the `embed-proving-key` feature.
+const EMBEDDED_PROVING_KEY: (&str, &str) = ("circuit_final.zkey", "ATOMIC_SWAP_PROVING_KEY_PATH");
+
 fn main() {
     let cargo_manifest_path = env::var("CARGO_MANIFEST_DIR").unwrap();
 
+    if env::var_os("CARGO_FEATURE_EMBED_ARTIFACTS").is_some() {
+        embed_artifacts(&cargo_manifest_path);
+    }
+
     compile_depositor_bindings(cargo_manifest_path)
 }
 
+fn embed_artifacts(cargo_manifest_path: &str) {
+    println!("cargo:rerun-if-env-changed=ATOMIC_SWAP_ARTIFACTS_DIR");
+
+    let artifacts_dir = env::var("ATOMIC_SWAP_ARTIFACTS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| Path::new(cargo_manifest_path).join(DEFAULT_ARTIFACTS_DIR));
+
+    let mut artifacts = vec![EMBEDDED_VERIFICATION_KEY];
+    if env::var_os("CARGO_FEATURE_PROVER").is_some() {
+        artifacts.push(EMBEDDED_WITNESS_CALCULATOR);
+    }
+    if env::var_os("CARGO_FEATURE_EMBED_PROVING_KEY").is_some() {
+        artifacts.push(EMBEDDED_PROVING_KEY);
+    }
+
+    for (file_name, env_name) in artifacts {
+        let path = artifacts_dir.join(file_name);
+        if !path.exists() {
+            panic!(
+                "{} is required to embed Circom artifacts, run ./scripts/atomic-swap-pre-build.sh \
+                 or set ATOMIC_SWAP_ARTIFACTS_DIR",
+                path.display()
+            );
+        }
+
+        println!("cargo:rerun-if-changed={}", path.display());
+        println!("cargo:rustc-env={env_name}={}", path.display());
+    }
+}
+
 fn compile_depositor_bindings(cargo_manifest_path: String) {
     let depositor_bindings_path =
         format!("{}/{}", cargo_manifest_path, DEPOSITOR_RUST_BINDINGS_PATH);
@@ -132,21 +132,12 @@ contract Depositor {
      * @param secretHash_ The Poseidon hash of the secret required for the recipient to withdraw the deposit.
      * @param lockTime_ The duration (in seconds) for which the deposit is locked and cannot be withdrawn.
      */
-    function deposit(address recipient_, bytes32 secretHash_, uint256 lockTime_) external payable {
-        if (msg.value == 0) revert ZeroDepositAmount();
-        if (deposits[secretHash_].amount != 0) revert DepositAlreadyExists(secretHash_);
-        if (lockTime_ < MIN_LOCK_TIME) revert LockTimeTooShort(lockTime_, MIN_LOCK_TIME);
-        if (recipient_ == address(0)) revert ZeroAddressNotAllowed();
-
-        deposits[secretHash_] = Deposit({
-            sender: msg.sender,
-            recipient: recipient_,
-            amount: msg.value,
-            lockTime: block.timestamp + lockTime_,
-            isWithdrawn: false
-        });
-
-        emit Deposited(msg.sender, recipient_, msg.value, lockTime_, secretHash_);
+    function deposit(
+        address recipient_,
+        bytes32 secretHash_,
+        uint256 lockTime_
+    ) external payable virtual {
+        _deposit(recipient_, secretHash_, lockTime_);
     }
 
     /**
@@ -156,53 +147,93 @@ contract Depositor {
      * @param secret_ The prototype of the `secretHash` used in the deposit function.
      */
     function withdraw(uint256 secret_) external {
-        bytes32 secretHash_ = _getSecretHash(secret_);
+        _withdraw(secret_, _getSecretHash(secret_), address(0), 0);
+    }
 
+    /**
+     * @notice Allows the sender to restore a deposit back to themselves after the lock time has expired.
+     * @dev Emits a `Restored` event upon successful restoration. Checks for non-existent, already withdrawn,
+     *      or not yet expired deposits.
+     * @param secretHash_ The Poseidon hash of the secret used when the deposit was created.
+     */
+    function restore(bytes32 secretHash_) external {
         Deposit storage userDeposit = deposits[secretHash_];
 
         uint256 depositAmount_ = userDeposit.amount;
-        address depositRecipient_ = userDeposit.recipient;
 
         if (depositAmount_ == 0) revert DepositDoesNotExist(secretHash_);
         if (userDeposit.isWithdrawn) revert DepositAlreadyWithdrawn(secretHash_);
+        if (userDeposit.lockTime > block.timestamp)
+            revert TimeLockNotExpired(block.timestamp, userDeposit.lockTime);
 
         userDeposit.isWithdrawn = true;
 
-        (bool success_, bytes memory data_) = payable(depositRecipient_).call{
+        (bool success_, bytes memory data_) = payable(userDeposit.sender).call{
             value: depositAmount_
         }("");
         Address.verifyCallResult(success_, data_);
 
-        emit Withdrawn(depositRecipient_, depositAmount_, secret_, secretHash_);
+        emit Restored(userDeposit.sender, depositAmount_, secretHash_);
     }
 
     /**
-     * @notice Allows the sender to restore a deposit back to themselves after the lock time has expired.
-     * @dev Emits a `Restored` event upon successful restoration. Checks for non-existent, already withdrawn,
-     *      or not yet expired deposits.
-     * @param secretHash_ The Poseidon hash of the secret used when the deposit was created.
+     * @notice Creates the deposit of `msg.value` for the recipient.
+     * @dev See `deposit` for the checks and the emitted event.
      */
-    function restore(bytes32 secretHash_) external {
+    function _deposit(address recipient_, bytes32 secretHash_, uint256 lockTime_) internal {
+        if (msg.value == 0) revert ZeroDepositAmount();
+        if (deposits[secretHash_].amount != 0) revert DepositAlreadyExists(secretHash_);
+        if (lockTime_ < MIN_LOCK_TIME) revert LockTimeTooShort(lockTime_, MIN_LOCK_TIME);
+        if (recipient_ == address(0)) revert ZeroAddressNotAllowed();
+
+        deposits[secretHash_] = Deposit({
+            sender: msg.sender,
+            recipient: recipient_,
+            amount: msg.value,
+            lockTime: block.timestamp + lockTime_,
+            isWithdrawn: false
+        });
+
+        emit Deposited(msg.sender, recipient_, msg.value, lockTime_, secretHash_);
+    }
+
+    /**
+     * @notice Withdraws the deposit to its recipient, paying the `fee_` out of it to the `relayer_`.
+     * @dev See `withdraw` for the checks and the emitted event. The event's amount is the one the
+     *      recipient receives.
+     */
+    function _withdraw(
+        uint256 secret_,
+        bytes32 secretHash_,
+        address relayer_,
+        uint256 fee_
+    ) internal {
         Deposit storage userDeposit = deposits[secretHash_];
 
         uint256 depositAmount_ = userDeposit.amount;
+        address depositRecipient_ = userDeposit.recipient;
 
         if (depositAmount_ == 0) revert DepositDoesNotExist(secretHash_);
         if (userDeposit.isWithdrawn) revert DepositAlreadyWithdrawn(secretHash_);
-        if (userDeposit.lockTime > block.timestamp)
-            revert TimeLockNotExpired(block.timestamp, userDeposit.lockTime);
 
         userDeposit.isWithdrawn = true;
 
-        (bool success_, bytes memory data_) = payable(userDeposit.sender).call{
-            value: depositAmount_
+        uint256 recipientAmount_ = depositAmount_ - fee_;
+
+        (bool success_, bytes memory data_) = payable(depositRecipient_).call{
+            value: recipientAmount_
         }("");
         Address.verifyCallResult(success_, data_);
 
-        emit Restored(userDeposit.sender, depositAmount_, secretHash_);
+        if (fee_ > 0) {
+            (success_, data_) = payable(relayer_).call{value: fee_}("");
+            Address.verifyCallResult(success_, data_);
+        }
+
+        emit Withdrawn(depositRecipient_, recipientAmount_, secret_, secretHash_);
     }
 
-    function _getSecretHash(uint256 secret_) private pure returns (bytes32) {
+    function _getSecretHash(uint256 secret_) internal pure returns (bytes32) {
         uint256 part1 = secret_ >> 192;
         uint256 part2 = ((secret_ >> 128) & 0xFFFFFFFFFFFFFFFF);
         uint256 part3 = ((secret_ >> 64) & 0xFFFFFFFFFFFFFFFF);
@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: MIT
+pragma solidity 0.8.23;
+
+import {EIP712} from "@openzeppelin/contracts/utils/cryptography/EIP712.sol";
+import {ECDSA} from "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+
+import {Depositor} from "./Depositor.sol";
+
+/**
+ * @title Taprootized Atomic Swaps Contract with relayed withdrawals
+ * @notice The `Depositor` whose deposits can be withdrawn by a relayer on behalf of the recipient.
+ *
+ * The recipient of a swap may have no ETH to pay for the `withdraw` gas, e.g. when swapping BTC into
+ * a fresh Ethereum address. Instead, the recipient signs the EIP-712 `Withdraw` message with the
+ * secret, the relayer's address, the relayer's fee and a deadline. The relayer submits it with
+ * `withdrawWithSignature` and receives the fee out of the deposit, while the rest goes to the
+ * recipient.
+ *
+ * Functionality:
+ * - The plain `withdraw` is still available to anyone.
+ * - The signature is bound to the relayer, so it can't be used by another account to take the fee.
+ */
+contract RelayedDepositor is Depositor, EIP712 {
+    /**
+     * @notice The EIP-712 type hash of the `Withdraw` message signed by the recipient.
+     */
+    bytes32 public constant WITHDRAW_TYPEHASH =
+        keccak256("Withdraw(uint256 secret,address relayer,uint256 fee,uint256 deadline)");
+
+    /**
+     * @notice Emitted when a deposit is withdrawn by a relayer.
+     * @param relayer The Ethereum address of the relayer that has submitted the withdrawal.
+     * @param fee The amount of ETH paid to the relayer out of the deposit.
+     * @param secretHash The Poseidon hash of the secret of the withdrawn deposit.
+     */
+    event RelayedWithdrawn(address indexed relayer, uint256 fee, bytes32 secretHash);
+
+    /**
+     * @notice Error thrown when the withdrawal signature is not made by the deposit's recipient.
+     * @param secretHash The Poseidon hash of the secret of the deposit.
+     */
+    error InvalidWithdrawSignature(bytes32 secretHash);
+
+    /**
+     * @notice Error thrown when the withdrawal signature is submitted after its deadline.
+     * @param deadline The UNIX timestamp until which the signature is valid.
+     */
+    error WithdrawSignatureExpired(uint256 deadline);
+
+    /**
+     * @notice Error thrown when the relayer's fee exceeds the deposit amount.
+     * @param fee The relayer's fee.
+     * @param amount The amount of the deposit.
+     */
+    error RelayerFeeTooHigh(uint256 fee, uint256 amount);
+
+    constructor() EIP712("RelayedDepositor", "1") {}
+
+    /**
+     * @notice Allows a relayer to withdraw a deposit to its recipient with the recipient's signature.
+     * @dev Emits `Withdrawn` and `RelayedWithdrawn` events upon successful withdrawal. The relayer is
+     *      the sender, and the fee is paid to it.
+     * @param secret_ The prototype of the `secretHash` used in the deposit function.
+     * @param fee_ The amount of ETH paid to the relayer out of the deposit.
+     * @param deadline_ The UNIX timestamp until which the signature is valid.
+     * @param signature_ The recipient's EIP-712 signature of the `Withdraw` message.
+     */
+    function withdrawWithSignature(
+        uint256 secret_,
+        uint256 fee_,
+        uint256 deadline_,
+        bytes calldata signature_
+    ) external {
+        if (block.timestamp > deadline_) revert WithdrawSignatureExpired(deadline_);
+
+        bytes32 secretHash_ = _getSecretHash(secret_);
+        Deposit storage userDeposit = deposits[secretHash_];
+
+        if (userDeposit.amount == 0) revert DepositDoesNotExist(secretHash_);
+        if (fee_ > userDeposit.amount) revert RelayerFeeTooHigh(fee_, userDeposit.amount);
+
+        bytes32 digest_ = _hashTypedDataV4(
+            keccak256(abi.encode(WITHDRAW_TYPEHASH, secret_, msg.sender, fee_, deadline_))
+        );
+        if (ECDSA.recover(digest_, signature_) != userDeposit.recipient) {
+            revert InvalidWithdrawSignature(secretHash_);
+        }
+
+        _withdraw(secret_, secretHash_, msg.sender, fee_);
+
+        emit RelayedWithdrawn(msg.sender, fee_, secretHash_);
+    }
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MIT
+pragma solidity 0.8.23;
+
+import {Depositor} from "./Depositor.sol";
+import {IAtomicSwapVerifier} from "./interfaces/IAtomicSwapVerifier.sol";
+
+/**
+ * @title Taprootized Atomic Swaps Contract with on-chain proof verification
+ * @notice The `Depositor` that can check the atomic swap zero-knowledge proof at deposit time.
+ *
+ * The proof binds the `secretHash` of the deposit to the secp256k1 public key `K = k * G` that the
+ * swap initiator has used in the Bitcoin taproot transaction. With it, the deposit can only be
+ * made for a secret whose public key is known, so the depositor doesn't have to rely on the
+ * off-chain proof verification alone.
+ *
+ * Functionality:
+ * - If `isProofRequired` is set, deposits can only be made with `depositWithProof`.
+ * - Otherwise, both `deposit` and `depositWithProof` are available.
+ */
+contract VerifiedDepositor is Depositor {
+    /**
+     * @notice Groth16 proof of the `atomic_swap` circuit in the form of Solidity calldata.
+     * @param a The proof's A point.
+     * @param b The proof's B point, with the coordinates in the `[c1, c0]` order.
+     * @param c The proof's C point.
+     */
+    struct SwapProof {
+        uint256[2] a;
+        uint256[2][2] b;
+        uint256[2] c;
+    }
+
+    /**
+     * @notice The verifier of the atomic swap proofs.
+     */
+    IAtomicSwapVerifier public immutable verifier;
+
+    /**
+     * @notice Whether the deposits can be made only with a valid atomic swap proof.
+     */
+    bool public immutable isProofRequired;
+
+    /**
+     * @notice Emitted when a deposit is made with a valid atomic swap proof.
+     * @param secretHash The Poseidon hash of the secret required to withdraw the deposit.
+     * @param pubkey The secp256k1 public key of the secret as 4 64-bit limbs of X and 4 of Y.
+     */
+    event SwapProofVerified(bytes32 secretHash, uint256[8] pubkey);
+
+    /**
+     * @notice Error thrown when a deposit is attempted without a proof while it is required.
+     */
+    error ProofRequired();
+
+    /**
+     * @notice Error thrown when the atomic swap proof is invalid.
+     * @param secretHash The Poseidon hash of the secret the proof was provided for.
+     */
+    error InvalidSwapProof(bytes32 secretHash);
+
+    /**
+     * @notice Error thrown when the contract is deployed with the zero verifier address.
+     */
+    error ZeroVerifierAddress();
+
+    constructor(IAtomicSwapVerifier verifier_, bool isProofRequired_) {
+        if (address(verifier_) == address(0)) revert ZeroVerifierAddress();
+
+        verifier = verifier_;
+        isProofRequired = isProofRequired_;
+    }
+
+    /**
+     * @inheritdoc Depositor
+     * @dev Reverts with `ProofRequired` if `isProofRequired` is set.
+     */
+    function deposit(
+        address recipient_,
+        bytes32 secretHash_,
+        uint256 lockTime_
+    ) external payable override {
+        if (isProofRequired) revert ProofRequired();
+
+        _deposit(recipient_, secretHash_, lockTime_);
+    }
+
+    /**
+     * @notice Allows a user to deposit ETH with the atomic swap proof that the secret hash belongs
+     *         to the secret of the given secp256k1 public key.
+     * @dev Emits `SwapProofVerified` and `Deposited` events upon successful deposit.
+     * @param recipient_ The Ethereum address of the recipient eligible to withdraw the deposit using the correct secret.
+     * @param secretHash_ The Poseidon hash of the secret required for the recipient to withdraw the deposit.
+     * @param lockTime_ The duration (in seconds) for which the deposit is locked and cannot be withdrawn.
+     * @param pubkey_ The secp256k1 public key of the secret as 4 64-bit limbs of X and 4 of Y, the
+     *        same as in the proof's public signals.
+     * @param proof_ The atomic swap proof.
+     */
+    function depositWithProof(
+        address recipient_,
+        bytes32 secretHash_,
+        uint256 lockTime_,
+        uint256[8] calldata pubkey_,
+        SwapProof calldata proof_
+    ) external payable {
+        uint256[9] memory pubSignals_;
+        for (uint256 i = 0; i < 8; i++) {
+            pubSignals_[i] = pubkey_[i];
+        }
+        pubSignals_[8] = uint256(secretHash_);
+
+        if (!verifier.verifyProof(proof_.a, proof_.b, proof_.c, pubSignals_)) {
+            revert InvalidSwapProof(secretHash_);
+        }
+
+        _deposit(recipient_, secretHash_, lockTime_);
+
+        emit SwapProofVerified(secretHash_, pubkey_);
+    }
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MIT
+pragma solidity 0.8.23;
+
+/**
+ * @title Atomic Swap Proof Verifier
+ * @notice Groth16 verifier of the `atomic_swap` circuit. Its implementation is generated from the
+ *         circuit's verification key, either by `snarkjs zkey export solidityverifier` or by
+ *         `rapidsnark::solidity_verifier`.
+ */
+interface IAtomicSwapVerifier {
+    /**
+     * @notice Verifies the atomic swap proof.
+     * @param pA_ The proof's A point.
+     * @param pB_ The proof's B point, with the coordinates in the `[c1, c0]` order.
+     * @param pC_ The proof's C point.
+     * @param pubSignals_ The circuit's public signals: 4 limbs of the pubkey's X, 4 limbs of the
+     *        pubkey's Y and the Poseidon hash of the secret.
+     * @return True if the proof is valid.
+     */
+    function verifyProof(
+        uint256[2] calldata pA_,
+        uint256[2][2] calldata pB_,
+        uint256[2] calldata pC_,
+        uint256[9] calldata pubSignals_
+    ) external view returns (bool);
+}
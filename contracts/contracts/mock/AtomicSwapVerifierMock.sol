@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MIT
+pragma solidity 0.8.23;
+
+import {IAtomicSwapVerifier} from "../interfaces/IAtomicSwapVerifier.sol";
+
+contract AtomicSwapVerifierMock is IAtomicSwapVerifier {
+    bool public isValid = true;
+
+    function setIsValid(bool isValid_) external {
+        isValid = isValid_;
+    }
+
+    function verifyProof(
+        uint256[2] calldata,
+        uint256[2][2] calldata,
+        uint256[2] calldata,
+        uint256[9] calldata
+    ) external view returns (bool) {
+        return isValid;
+    }
+}
@@ -11,6 +11,8 @@ import { Depositor } from "@ethers-v6";
 import { Reverter, getPoseidon } from "@utils";
 import { impersonateAccount, setBalance, time } from "@nomicfoundation/hardhat-network-helpers";
 
+import POSEIDON_VECTORS from "./data/poseidon-vectors.json";
+
 describe("Taprootized Atomic Swaps", () => {
   const reverter = new Reverter();
 
@@ -130,6 +132,18 @@ describe("Taprootized Atomic Swaps", () => {
       .withArgs(USER2.address, DEPOSIT_AMOUNT, secret, secretHash);
   });
 
+  it("should hash the secret as the Rust implementation does", async () => {
+    for (const { secret, limbs, hash } of POSEIDON_VECTORS) {
+      expect(ethers.toBeHex(Poseidon.hash(limbs.map((v) => BigInt(v))), 32)).to.equal(hash);
+
+      await depositor.deposit(USER2.address, hash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+      await expect(depositor.withdraw(secret))
+        .to.emit(depositor, "Withdrawn")
+        .withArgs(USER2.address, DEPOSIT_AMOUNT, secret, hash);
+    }
+  });
+
   it("should prevent double withdrawal with same secret", async () => {
     const [secret, secretHash] = generateSecret();
 
@@ -0,0 +1,165 @@
+import { expect } from "chai";
+import { ethers } from "hardhat";
+
+import { Poseidon } from "@iden3/js-crypto";
+
+import { SignerWithAddress } from "@nomicfoundation/hardhat-ethers/signers";
+import { time } from "@nomicfoundation/hardhat-network-helpers";
+
+import { RelayedDepositor } from "@ethers-v6";
+
+import { Reverter, getPoseidon } from "@utils";
+
+describe("Taprootized Atomic Swaps with relayed withdrawals", () => {
+  const reverter = new Reverter();
+
+  let USER1: SignerWithAddress;
+  let USER2: SignerWithAddress;
+  let RELAYER: SignerWithAddress;
+
+  let depositor: RelayedDepositor;
+
+  const LOCK_TIME = 3600; // One hour in seconds
+  const DEPOSIT_AMOUNT = ethers.parseEther("1");
+  const FEE = ethers.parseEther("0.01");
+
+  before("setup", async () => {
+    [USER1, USER2, RELAYER] = await ethers.getSigners();
+
+    const RelayedDepositorFactory = await ethers.getContractFactory("RelayedDepositor", {
+      libraries: {
+        PoseidonUnit4L: await (await getPoseidon(4)).getAddress(),
+      },
+    });
+    depositor = await RelayedDepositorFactory.deploy();
+
+    await reverter.snapshot();
+  });
+
+  afterEach(reverter.revert);
+
+  function generateSecret(): [string, string] {
+    const parts = [1, 2, 3, 4].map(() => ethers.hexlify(ethers.randomBytes(8)));
+    const wholeSecret =
+      "0x" +
+      parts
+        .reverse()
+        .map((hexString: string) => hexString.replace("0x", ""))
+        .join("");
+
+    const inputs = parts.reverse().map((v) => BigInt(v));
+    const secretHash = ethers.toBeHex(Poseidon.hash(inputs), 32);
+
+    return [wholeSecret, secretHash];
+  }
+
+  async function signWithdraw(
+    signer: SignerWithAddress,
+    secret: string,
+    relayer: string,
+    fee: bigint,
+    deadline: number
+  ): Promise<string> {
+    const domain = {
+      name: "RelayedDepositor",
+      version: "1",
+      chainId: (await ethers.provider.getNetwork()).chainId,
+      verifyingContract: await depositor.getAddress(),
+    };
+    const types = {
+      Withdraw: [
+        { name: "secret", type: "uint256" },
+        { name: "relayer", type: "address" },
+        { name: "fee", type: "uint256" },
+        { name: "deadline", type: "uint256" },
+      ],
+    };
+
+    return signer.signTypedData(domain, types, { secret, relayer, fee, deadline });
+  }
+
+  it("should withdraw with the recipient's signature and pay the relayer", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + LOCK_TIME;
+
+    await depositor.connect(USER1).deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+    const signature = await signWithdraw(USER2, secret, RELAYER.address, FEE, deadline);
+
+    const tx = depositor.connect(RELAYER).withdrawWithSignature(secret, FEE, deadline, signature);
+    await expect(tx)
+      .to.emit(depositor, "Withdrawn")
+      .withArgs(USER2.address, DEPOSIT_AMOUNT - FEE, secret, secretHash)
+      .and.to.emit(depositor, "RelayedWithdrawn")
+      .withArgs(RELAYER.address, FEE, secretHash);
+    await expect(tx).to.changeEtherBalance(USER2, DEPOSIT_AMOUNT - FEE);
+  });
+
+  it("should reject signature of another account than the recipient", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + LOCK_TIME;
+
+    await depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+    const signature = await signWithdraw(USER1, secret, RELAYER.address, FEE, deadline);
+
+    await expect(depositor.connect(RELAYER).withdrawWithSignature(secret, FEE, deadline, signature))
+      .to.be.revertedWithCustomError(depositor, "InvalidWithdrawSignature")
+      .withArgs(secretHash);
+  });
+
+  it("should reject signature submitted by another relayer", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + LOCK_TIME;
+
+    await depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+    const signature = await signWithdraw(USER2, secret, RELAYER.address, FEE, deadline);
+
+    await expect(depositor.connect(USER1).withdrawWithSignature(secret, FEE, deadline, signature))
+      .to.be.revertedWithCustomError(depositor, "InvalidWithdrawSignature")
+      .withArgs(secretHash);
+  });
+
+  it("should reject expired signature", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + 10;
+
+    await depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+    const signature = await signWithdraw(USER2, secret, RELAYER.address, FEE, deadline);
+    await time.increaseTo(deadline + 1);
+
+    await expect(depositor.connect(RELAYER).withdrawWithSignature(secret, FEE, deadline, signature))
+      .to.be.revertedWithCustomError(depositor, "WithdrawSignatureExpired")
+      .withArgs(deadline);
+  });
+
+  it("should reject fee exceeding the deposit", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + LOCK_TIME;
+    const fee = DEPOSIT_AMOUNT + 1n;
+
+    await depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+
+    const signature = await signWithdraw(USER2, secret, RELAYER.address, fee, deadline);
+
+    await expect(depositor.connect(RELAYER).withdrawWithSignature(secret, fee, deadline, signature))
+      .to.be.revertedWithCustomError(depositor, "RelayerFeeTooHigh")
+      .withArgs(fee, DEPOSIT_AMOUNT);
+  });
+
+  it("should prevent relayed withdrawal of withdrawn deposit", async () => {
+    const [secret, secretHash] = generateSecret();
+    const deadline = (await time.latest()) + LOCK_TIME;
+
+    await depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT });
+    await depositor.withdraw(secret);
+
+    const signature = await signWithdraw(USER2, secret, RELAYER.address, FEE, deadline);
+
+    await expect(depositor.connect(RELAYER).withdrawWithSignature(secret, FEE, deadline, signature))
+      .to.be.revertedWithCustomError(depositor, "DepositAlreadyWithdrawn")
+      .withArgs(secretHash);
+  });
+});
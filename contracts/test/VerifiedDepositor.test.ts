@@ -0,0 +1,106 @@
+import { expect } from "chai";
+import { ethers } from "hardhat";
+
+import { SignerWithAddress } from "@nomicfoundation/hardhat-ethers/signers";
+
+import { AtomicSwapVerifierMock, VerifiedDepositor } from "@ethers-v6";
+
+import { Reverter, getPoseidon } from "@utils";
+
+describe("Taprootized Atomic Swaps with proof verification", () => {
+  const reverter = new Reverter();
+
+  let USER1: SignerWithAddress;
+  let USER2: SignerWithAddress;
+
+  let verifier: AtomicSwapVerifierMock;
+  let depositor: VerifiedDepositor;
+  let strictDepositor: VerifiedDepositor;
+
+  const LOCK_TIME = 3600; // One hour in seconds
+  const DEPOSIT_AMOUNT = ethers.parseEther("1");
+
+  const PUBKEY = [1, 2, 3, 4, 5, 6, 7, 8];
+  const PROOF = {
+    a: [1, 2],
+    b: [
+      [3, 4],
+      [5, 6],
+    ],
+    c: [7, 8],
+  };
+
+  before("setup", async () => {
+    [USER1, USER2] = await ethers.getSigners();
+
+    verifier = await ethers.deployContract("AtomicSwapVerifierMock");
+
+    const VerifiedDepositorFactory = await ethers.getContractFactory("VerifiedDepositor", {
+      libraries: {
+        PoseidonUnit4L: await (await getPoseidon(4)).getAddress(),
+      },
+    });
+    depositor = await VerifiedDepositorFactory.deploy(await verifier.getAddress(), false);
+    strictDepositor = await VerifiedDepositorFactory.deploy(await verifier.getAddress(), true);
+
+    await reverter.snapshot();
+  });
+
+  afterEach(reverter.revert);
+
+  it("should reject zero verifier address", async () => {
+    const VerifiedDepositorFactory = await ethers.getContractFactory("VerifiedDepositor", {
+      libraries: {
+        PoseidonUnit4L: await (await getPoseidon(4)).getAddress(),
+      },
+    });
+
+    await expect(VerifiedDepositorFactory.deploy(ethers.ZeroAddress, true)).to.be.revertedWithCustomError(
+      depositor,
+      "ZeroVerifierAddress"
+    );
+  });
+
+  it("should allow deposit without proof if it isn't required", async () => {
+    const secretHash = ethers.hexlify(ethers.randomBytes(32));
+
+    await expect(depositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT })).to.emit(
+      depositor,
+      "Deposited"
+    );
+  });
+
+  it("should reject deposit without proof if it is required", async () => {
+    const secretHash = ethers.hexlify(ethers.randomBytes(32));
+
+    await expect(
+      strictDepositor.deposit(USER2.address, secretHash, LOCK_TIME, { value: DEPOSIT_AMOUNT })
+    ).to.be.revertedWithCustomError(strictDepositor, "ProofRequired");
+  });
+
+  it("should deposit with valid proof", async () => {
+    const secretHash = ethers.hexlify(ethers.randomBytes(32));
+
+    await expect(
+      strictDepositor
+        .connect(USER1)
+        .depositWithProof(USER2.address, secretHash, LOCK_TIME, PUBKEY, PROOF, { value: DEPOSIT_AMOUNT })
+    )
+      .to.emit(strictDepositor, "SwapProofVerified")
+      .withArgs(secretHash, PUBKEY)
+      .and.to.emit(strictDepositor, "Deposited")
+      .withArgs(USER1.address, USER2.address, DEPOSIT_AMOUNT, LOCK_TIME, secretHash);
+  });
+
+  it("should reject deposit with invalid proof", async () => {
+    const secretHash = ethers.hexlify(ethers.randomBytes(32));
+
+    await verifier.setIsValid(false);
+
+    await expect(
+      strictDepositor.depositWithProof(USER2.address, secretHash, LOCK_TIME, PUBKEY, PROOF, { value: DEPOSIT_AMOUNT })
+    )
+      .to.be.revertedWithCustomError(strictDepositor, "InvalidSwapProof")
+      .withArgs(secretHash);
+  });
+});
@@ -0,0 +1,422 @@
+use ark_bn254::{Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{PrimeField, Zero};
+use num::BigUint;
+use std::fmt::{self, Write};
+
+use crate::verifier::{Error, VerificationKey, ZkProof};
+
+/// Groth16 proof with its public inputs in the form the Solidity verifier's
+/// `verifyProof(uint[2], uint[2][2], uint[2], uint[N])` expects.
+///
+/// The G2 point coordinates are in the `[c1, c0]` order the EVM pairing precompile uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityCalldata {
+    pub a: [BigUint; 2],
+    pub b: [[BigUint; 2]; 2],
+    pub c: [BigUint; 2],
+    pub inputs: Vec<BigUint>,
+}
+
+impl SolidityCalldata {
+    pub fn new(proof: &ZkProof, public_inputs: &[Fr]) -> Self {
+        Self {
+            a: g1_to_uints(&proof.a),
+            b: g2_to_uints(&proof.b),
+            c: g1_to_uints(&proof.c),
+            inputs: public_inputs.iter().map(fr_to_uint).collect(),
+        }
+    }
+}
+
+/// Formats the calldata as `snarkjs zkey export soliditycalldata` does.
+impl fmt::Display for SolidityCalldata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |value: &BigUint| format!("\"0x{:064x}\"", value);
+        let list = |values: &[BigUint], separator| {
+            values.iter().map(hex).collect::<Vec<_>>().join(separator)
+        };
+
+        // snarkjs separates the coordinates with a space, but not the public inputs.
+        write!(
+            f,
+            "[{}],[[{}],[{}]],[{}],[{}]",
+            list(&self.a, ", "),
+            list(&self.b[0], ", "),
+            list(&self.b[1], ", "),
+            list(&self.c, ", "),
+            list(&self.inputs, ","),
+        )
+    }
+}
+
+/// Solidity keywords and reserved words that can't name a contract, apart from the sized
+/// elementary types, see [`is_sized_elementary_type`].
+const SOLIDITY_KEYWORDS: &str =
+    "abstract address after alias anonymous apply as assembly auto bool break byte bytes calldata \
+     case catch constant constructor continue contract copyof default define delete do else emit \
+     enum event external fallback false final fixed for function hex if immutable implements \
+     import in indexed inline int interface internal is let library macro mapping match memory \
+     modifier mutable new null of override partial payable pragma private promise public pure \
+     receive reference relocatable return returns sealed sizeof static storage string struct \
+     supports switch true try type typedef typeof ufixed uint unchecked unicode using var view \
+     virtual while";
+
+/// Generates the Solidity Groth16 verifier for the verification key, equivalent to the one from
+/// `snarkjs zkey export solidityverifier`. The key must have public inputs, as Solidity doesn't
+/// have zero-length arrays, and the contract name must be a Solidity identifier, as it's pasted
+/// into the source.
+pub fn solidity_verifier(
+    verification_key: &VerificationKey,
+    contract_name: &str,
+) -> Result<String, Error> {
+    if !is_solidity_identifier(contract_name) {
+        return Err(Error::InvalidContractName(contract_name.to_owned()));
+    }
+
+    let n_public = verification_key.gamma_abc_g1.len().saturating_sub(1);
+    if n_public == 0 {
+        return Err(Error::NoPublicInputs);
+    }
+
+    let mut src = String::new();
+
+    let [alpha_x, alpha_y] = g1_to_uints(&verification_key.alpha_g1);
+    let [[beta_x1, beta_x2], [beta_y1, beta_y2]] = g2_to_uints(&verification_key.beta_g2);
+    let [[gamma_x1, gamma_x2], [gamma_y1, gamma_y2]] = g2_to_uints(&verification_key.gamma_g2);
+    let [[delta_x1, delta_x2], [delta_y1, delta_y2]] = g2_to_uints(&verification_key.delta_g2);
+
+    // Writing to String can't fail, so the results are ignored below.
+    let _ = writeln!(
+        src,
+        "// SPDX-License-Identifier: GPL-3.0
+pragma solidity >=0.7.0 <0.9.0;
+
+contract {contract_name} {{
+    // Scalar field size
+    uint256 constant r = {r};
+    // Base field size
+    uint256 constant q = {q};
+
+    // Verification Key data
+    uint256 constant alphax = {alpha_x};
+    uint256 constant alphay = {alpha_y};
+    uint256 constant betax1 = {beta_x1};
+    uint256 constant betax2 = {beta_x2};
+    uint256 constant betay1 = {beta_y1};
+    uint256 constant betay2 = {beta_y2};
+    uint256 constant gammax1 = {gamma_x1};
+    uint256 constant gammax2 = {gamma_x2};
+    uint256 constant gammay1 = {gamma_y1};
+    uint256 constant gammay2 = {gamma_y2};
+    uint256 constant deltax1 = {delta_x1};
+    uint256 constant deltax2 = {delta_x2};
+    uint256 constant deltay1 = {delta_y1};
+    uint256 constant deltay2 = {delta_y2};
+",
+        r = BigUint::from(Fr::MODULUS),
+        q = BigUint::from(Fq::MODULUS),
+    );
+
+    for (i, ic) in verification_key.gamma_abc_g1.iter().enumerate() {
+        let [x, y] = g1_to_uints(ic);
+        let _ = writeln!(src, "    uint256 constant IC{i}x = {x};");
+        let _ = writeln!(src, "    uint256 constant IC{i}y = {y};");
+    }
+
+    let _ = writeln!(
+        src,
+        "
+    // Memory data
+    uint16 constant pVk = 0;
+    uint16 constant pPairing = 128;
+
+    uint16 constant pLastMem = 896;
+
+    function verifyProof(
+        uint[2] calldata _pA,
+        uint[2][2] calldata _pB,
+        uint[2] calldata _pC,
+        uint[{n_public}] calldata _pubSignals
+    ) public view returns (bool) {{
+        assembly {{
+            function checkField(v) {{
+                if iszero(lt(v, r)) {{
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }}
+            }}
+
+            // G1 function to multiply a G1 value(x,y) to value in an address
+            function g1_mulAccC(pR, x, y, s) {{
+                let success
+                let mIn := mload(0x40)
+                mstore(mIn, x)
+                mstore(add(mIn, 32), y)
+                mstore(add(mIn, 64), s)
+
+                success := staticcall(sub(gas(), 2000), 7, mIn, 96, mIn, 64)
+
+                if iszero(success) {{
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }}
+
+                mstore(add(mIn, 64), mload(pR))
+                mstore(add(mIn, 96), mload(add(pR, 32)))
+
+                success := staticcall(sub(gas(), 2000), 6, mIn, 128, pR, 64)
+
+                if iszero(success) {{
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }}
+            }}
+
+            function checkPairing(pA, pB, pC, pubSignals, pMem) -> isOk {{
+                let _pPairing := add(pMem, pPairing)
+                let _pVk := add(pMem, pVk)
+
+                mstore(_pVk, IC0x)
+                mstore(add(_pVk, 32), IC0y)
+
+                // Compute the linear combination vk_x"
+    );
+
+    for i in 0..n_public {
+        let _ = writeln!(
+            src,
+            "                g1_mulAccC(_pVk, IC{}x, IC{}y, calldataload(add(pubSignals, {})))",
+            i + 1,
+            i + 1,
+            i * 32
+        );
+    }
+
+    let _ = writeln!(
+        src,
+        "
+                // -A
+                mstore(_pPairing, calldataload(pA))
+                mstore(add(_pPairing, 32), mod(sub(q, calldataload(add(pA, 32))), q))
+
+                // B
+                mstore(add(_pPairing, 64), calldataload(pB))
+                mstore(add(_pPairing, 96), calldataload(add(pB, 32)))
+                mstore(add(_pPairing, 128), calldataload(add(pB, 64)))
+                mstore(add(_pPairing, 160), calldataload(add(pB, 96)))
+
+                // alpha1
+                mstore(add(_pPairing, 192), alphax)
+                mstore(add(_pPairing, 224), alphay)
+
+                // beta2
+                mstore(add(_pPairing, 256), betax1)
+                mstore(add(_pPairing, 288), betax2)
+                mstore(add(_pPairing, 320), betay1)
+                mstore(add(_pPairing, 352), betay2)
+
+                // vk_x
+                mstore(add(_pPairing, 384), mload(add(pMem, pVk)))
+                mstore(add(_pPairing, 416), mload(add(pMem, add(pVk, 32))))
+
+                // gamma2
+                mstore(add(_pPairing, 448), gammax1)
+                mstore(add(_pPairing, 480), gammax2)
+                mstore(add(_pPairing, 512), gammay1)
+                mstore(add(_pPairing, 544), gammay2)
+
+                // C
+                mstore(add(_pPairing, 576), calldataload(pC))
+                mstore(add(_pPairing, 608), calldataload(add(pC, 32)))
+
+                // delta2
+                mstore(add(_pPairing, 640), deltax1)
+                mstore(add(_pPairing, 672), deltax2)
+                mstore(add(_pPairing, 704), deltay1)
+                mstore(add(_pPairing, 736), deltay2)
+
+                let success := staticcall(sub(gas(), 2000), 8, _pPairing, 768, _pPairing, 0x20)
+
+                isOk := and(success, mload(_pPairing))
+            }}
+
+            let pMem := mload(0x40)
+            mstore(0x40, add(pMem, pLastMem))
+
+            // Validate that all evaluations ∈ F"
+    );
+
+    for i in 0..n_public {
+        let _ = writeln!(
+            src,
+            "            checkField(calldataload(add(_pubSignals, {})))",
+            i * 32
+        );
+    }
+
+    let _ = write!(
+        src,
+        "
+            // Validate all evaluations
+            let isValid := checkPairing(_pA, _pB, _pC, _pubSignals, pMem)
+
+            mstore(0, isValid)
+            return(0, 0x20)
+        }}
+    }}
+}}
+"
+    );
+
+    Ok(src)
+}
+
+fn fq_to_uint(value: &Fq) -> BigUint {
+    value.into_bigint().into()
+}
+
+fn fr_to_uint(value: &Fr) -> BigUint {
+    value.into_bigint().into()
+}
+
+/// Returns `[x, y]` of the point, `[0, 0]` being the point at infinity as the EVM precompiles
+/// expect.
+fn g1_to_uints(point: &G1Affine) -> [BigUint; 2] {
+    match point.xy() {
+        Some((x, y)) => [fq_to_uint(x), fq_to_uint(y)],
+        None => [BigUint::zero(), BigUint::zero()],
+    }
+}
+
+/// Returns `[[x.c1, x.c0], [y.c1, y.c0]]` of the point, all zeros being the point at infinity
+/// as the EVM precompiles expect.
+fn g2_to_uints(point: &G2Affine) -> [[BigUint; 2]; 2] {
+    let fq2_to_uints = |value: &Fq2| [fq_to_uint(&value.c1), fq_to_uint(&value.c0)];
+
+    match point.xy() {
+        Some((x, y)) => [fq2_to_uints(x), fq2_to_uints(y)],
+        None => [
+            [BigUint::zero(), BigUint::zero()],
+            [BigUint::zero(), BigUint::zero()],
+        ],
+    }
+}
+
+/// Whether the `name` matches `[a-zA-Z$_][a-zA-Z0-9$_]*` and isn't a keyword.
+fn is_solidity_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    (first.is_ascii_alphabetic() || first == '$' || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '$' || c == '_')
+        && !SOLIDITY_KEYWORDS
+            .split_whitespace()
+            .any(|keyword| keyword == name)
+        && !is_sized_elementary_type(name)
+}
+
+/// Whether the `name` is a sized elementary type, e.g. `uint256`, `bytes32` or `fixed128x18`.
+fn is_sized_elementary_type(name: &str) -> bool {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    ["uint", "int", "bytes"]
+        .iter()
+        .any(|prefix| name.strip_prefix(prefix).is_some_and(is_number))
+        || ["ufixed", "fixed"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|size| size.split_once('x'))
+                .is_some_and(|(m, n)| is_number(m) && is_number(n))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use ark_bn254::G1Affine;
+    use ark_ec::AffineRepr;
+
+    use super::{solidity_verifier, SolidityCalldata};
+    use crate::verifier::{Error, FromJson, PublicInputs, VerificationKey, ZkProof};
+
+    const PROOF_JSON: &str = include_str!("../tests/data/proof.json");
+    const PUBLIC_JSON: &str = include_str!("../tests/data/public.json");
+    const VERIFICATION_KEY_JSON: &str = include_str!("../tests/data/verification_key.json");
+
+    /// What `snarkjs zkey export soliditycalldata` prints for the proof.
+    const CALLDATA: &str = include_str!("../tests/data/calldata.txt");
+
+    #[test]
+    fn test_solidity_calldata() {
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        let public_inputs = PublicInputs::from_json(PUBLIC_JSON.as_bytes()).unwrap();
+
+        let calldata = SolidityCalldata::new(&proof, &public_inputs);
+
+        assert_eq!(calldata.to_string(), CALLDATA.trim_end());
+    }
+
+    #[test]
+    fn test_solidity_verifier() {
+        let verification_key =
+            VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+
+        let src = solidity_verifier(&verification_key, "Groth16Verifier").unwrap();
+
+        assert!(src.contains("contract Groth16Verifier {"));
+        assert!(src.contains("uint[1] calldata _pubSignals"));
+        assert!(src.contains("uint256 constant IC1x = "));
+        assert!(!src.contains("IC2x"));
+    }
+
+    #[test]
+    fn test_solidity_verifier_contract_name() {
+        let verification_key =
+            VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+
+        for name in [
+            "Verifier",
+            "_Verifier2",
+            "$verifier",
+            "uint256Verifier",
+            "fixedx",
+        ] {
+            assert!(solidity_verifier(&verification_key, name).is_ok(), "{name}");
+        }
+
+        for name in [
+            "",
+            "2Verifier",
+            "Groth16 Verifier",
+            "Verifier { } contract Evil",
+            "Verifier\n",
+            "Vérifier",
+            "contract",
+            "address",
+            "uint256",
+            "bytes32",
+            "fixed128x18",
+        ] {
+            assert!(
+                matches!(
+                    solidity_verifier(&verification_key, name),
+                    Err(Error::InvalidContractName(_))
+                ),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_solidity_verifier_without_public_inputs() {
+        let mut verification_key =
+            VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        verification_key.gamma_abc_g1 = vec![G1Affine::generator()];
+
+        assert!(matches!(
+            solidity_verifier(&verification_key, "Groth16Verifier"),
+            Err(Error::NoPublicInputs)
+        ));
+    }
+}
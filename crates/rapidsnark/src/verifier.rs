@@ -1,14 +1,18 @@
-use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine};
 use ark_crypto_primitives::snark::SNARK;
-use ark_ec::pairing::Pairing;
-use ark_ff::QuadExtField;
-use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger256, PrimeField, UniformRand, Zero};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
 use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::rand::RngCore;
 use num::bigint::ParseBigIntError;
 use num::BigUint;
 use std::str::FromStr;
 
-pub type PublicInputs = Vec<<Bn254 as Pairing>::ScalarField>;
+pub type PublicInputs = Vec<Fr>;
 pub type ZkProof = Proof<Bn254>;
 pub type VerificationKey = VerifyingKey<Bn254>;
 
@@ -20,22 +24,144 @@ pub enum Error {
     ParsePoints(#[from] ParseBigIntError),
     #[error("invalid verification key or proof")]
     VerifyProof(#[from] SynthesisError),
+    #[error("value is not a canonical field element: {0}")]
+    NotInField(String),
+    #[error("projective point has invalid z-coordinate")]
+    InvalidProjectiveZ,
+    #[error("point is not on the curve")]
+    PointNotOnCurve,
+    #[error("point is not in the correct subgroup")]
+    PointNotInSubgroup,
+    #[error("expected {expected} public inputs, got {actual}")]
+    PublicInputsNumberMismatch { expected: usize, actual: usize },
+    #[error("unsupported protocol: {0}")]
+    UnsupportedProtocol(String),
+    #[error("unsupported curve: {0}")]
+    UnsupportedCurve(String),
+    #[error("failed to (de)serialize binary encoding: {0}")]
+    Binary(#[from] SerializationError),
+    #[error("{0} trailing bytes after binary encoding")]
+    TrailingBytes(usize),
+    #[error("Solidity verifier needs at least one public input")]
+    NoPublicInputs,
+    #[error("invalid Solidity contract name: {0:?}")]
+    InvalidContractName(String),
 }
 
+/// Verifies the snarkjs Groth16 `proof` against `pubsignals` with the verification key.
+///
+/// All the inputs come from the counterparty, so they are parsed strictly: points must be on the
+/// curve and in the correct subgroup, field elements must be canonical and the number of public
+/// inputs must match the verification key. Use [`Verifier`] to check several proofs against the
+/// same key.
 pub fn groth16_verifier(
     verification_key_json: &[u8],
     proof_json: &[u8],
     pubsignals_json: &[u8],
 ) -> Result<bool, Error> {
-    let proof = ZkProof::from_json(proof_json).unwrap();
-    let verification_key: VerificationKey = VerificationKey::from_json(verification_key_json)?;
-    let public_inputs = PublicInputs::from_json(pubsignals_json)?;
+    Verifier::from_json(verification_key_json)?.verify_json(proof_json, pubsignals_json)
+}
+
+/// Groth16 verifier with the verification key prepared once, so it can be reused for many proofs.
+pub struct Verifier {
+    pvk: PreparedVerifyingKey<Bn254>,
+}
+
+impl Verifier {
+    pub fn new(verification_key: VerificationKey) -> Result<Self, Error> {
+        let pvk = Groth16::<Bn254>::process_vk(&verification_key)?;
+
+        Ok(Self { pvk })
+    }
+
+    /// Creates the verifier from the snarkjs `verification_key.json`.
+    pub fn from_json(verification_key_json: &[u8]) -> Result<Self, Error> {
+        Self::new(VerificationKey::from_json(verification_key_json)?)
+    }
+
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.pvk.vk
+    }
+
+    pub fn verify(&self, proof: &ZkProof, public_inputs: &[Fr]) -> Result<bool, Error> {
+        check_public_inputs_number(&self.pvk.vk, public_inputs)?;
+
+        let result = Groth16::<Bn254>::verify_with_processed_vk(&self.pvk, public_inputs, proof)?;
 
-    let pvk = Groth16::<Bn254>::process_vk(&verification_key)?;
+        Ok(result)
+    }
+
+    /// Verifies the snarkjs `proof.json` against `public.json`.
+    pub fn verify_json(&self, proof_json: &[u8], pubsignals_json: &[u8]) -> Result<bool, Error> {
+        let proof = ZkProof::from_json(proof_json)?;
+        let public_inputs = PublicInputs::from_json(pubsignals_json)?;
+
+        self.verify(&proof, &public_inputs)
+    }
+
+    /// Verifies all the proofs at once with a random linear combination of their pairing
+    /// equations, which costs a single multi-pairing instead of one per proof.
+    ///
+    /// Returns `true` only if every proof is valid, except with negligible probability, so an
+    /// empty batch is valid. If it returns `false`, use [`Self::verify`] to find out which proofs
+    /// are invalid.
+    pub fn verify_batch<R: RngCore>(
+        &self,
+        proofs: &[(ZkProof, PublicInputs)],
+        rng: &mut R,
+    ) -> Result<bool, Error> {
+        if proofs.is_empty() {
+            return Ok(true);
+        }
+
+        let mut g1: Vec<<Bn254 as Pairing>::G1Prepared> = Vec::with_capacity(proofs.len() + 2);
+        let mut g2: Vec<<Bn254 as Pairing>::G2Prepared> = Vec::with_capacity(proofs.len() + 2);
+
+        let mut randomizers_sum = Fr::zero();
+        let mut prepared_inputs_sum = G1Projective::zero();
+        let mut c_sum = G1Projective::zero();
 
-    let result = Groth16::<Bn254>::verify_with_processed_vk(&pvk, &public_inputs as &[Fr], &proof)?;
+        for (proof, public_inputs) in proofs {
+            check_public_inputs_number(&self.pvk.vk, public_inputs)?;
 
-    Ok(result)
+            let randomizer = Fr::rand(rng);
+            randomizers_sum += randomizer;
+
+            let prepared_inputs = Groth16::<Bn254>::prepare_inputs(&self.pvk, public_inputs)?;
+            prepared_inputs_sum += prepared_inputs * randomizer;
+            c_sum += proof.c * randomizer;
+
+            g1.push((proof.a * randomizer).into_affine().into());
+            g2.push(proof.b.into());
+        }
+
+        // Π e(r·A, B) · e(Σ r·L, -γ) · e(Σ r·C, -δ) == e(α, β)^(Σ r)
+        g1.push(prepared_inputs_sum.into_affine().into());
+        g2.push(self.pvk.gamma_g2_neg_pc.clone());
+        g1.push(c_sum.into_affine().into());
+        g2.push(self.pvk.delta_g2_neg_pc.clone());
+
+        let lhs = Bn254::multi_pairing(g1, g2);
+        let rhs = PairingOutput(self.pvk.alpha_g1_beta_g2) * randomizers_sum;
+
+        Ok(lhs == rhs)
+    }
+}
+
+/// Checks that the verification key has an `IC` point for each public input plus the constant one.
+pub fn check_public_inputs_number(
+    verification_key: &VerificationKey,
+    public_inputs: &[Fr],
+) -> Result<(), Error> {
+    let expected = verification_key.gamma_abc_g1.len().saturating_sub(1);
+    if public_inputs.len() != expected {
+        return Err(Error::PublicInputsNumberMismatch {
+            expected,
+            actual: public_inputs.len(),
+        });
+    }
+
+    Ok(())
 }
 
 pub trait FromJson {
@@ -44,11 +170,41 @@ pub trait FromJson {
     fn from_json(src: &[u8]) -> Result<Self::Output, Error>;
 }
 
+/// Serializes into the snarkjs JSON format, the one [`FromJson`] reads.
+pub trait ToJson {
+    fn to_json(&self) -> Result<Vec<u8>, Error>;
+}
+
+/// Compact binary encoding: the canonical compressed arkworks serialization.
+///
+/// A proof takes 128 bytes instead of about 700 bytes of the snarkjs JSON.
+pub trait ToBytes {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error>;
+}
+
+/// Parses the encoding of [`ToBytes`]. Points are checked to be on the curve and in the correct
+/// subgroup, as [`FromJson`] does.
+pub trait FromBytes {
+    type Output;
+
+    fn from_bytes(src: &[u8]) -> Result<Self::Output, Error>;
+}
+
+/// The only protocol supported by the verifier.
+pub const PROTOCOL: &str = "groth16";
+
+/// The snarkjs name of the BN254 curve.
+pub const CURVE: &str = "bn128";
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct ZkProofIntermidiateRepr {
     pub pi_a: [String; 3],
     pub pi_b: [[String; 2]; 3],
     pub pi_c: [String; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curve: Option<String>,
 }
 
 impl FromJson for ZkProof {
@@ -57,62 +213,56 @@ impl FromJson for ZkProof {
     fn from_json(src: &[u8]) -> Result<Self::Output, Error> {
         let interm_repr: ZkProofIntermidiateRepr = serde_json::from_slice(src)?;
 
-        let pi_a = G1Affine::new(
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(interm_repr.pi_a[0].as_str())?),
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(interm_repr.pi_a[1].as_str())?),
-        );
-
-        let pi_b = G2Affine::new(
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.pi_b[0][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.pi_b[0][1].as_str(),
-                )?),
-            },
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.pi_b[1][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.pi_b[1][1].as_str(),
-                )?),
-            },
-        );
-
-        let pi_c = G1Affine::new(
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(interm_repr.pi_c[0].as_str())?),
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(interm_repr.pi_c[1].as_str())?),
-        );
+        check_protocol_and_curve(&interm_repr.protocol, &interm_repr.curve)?;
 
         Ok(Self {
-            a: pi_a,
-            b: pi_b,
-            c: pi_c,
+            a: parse_g1(&interm_repr.pi_a)?,
+            b: parse_g2(&interm_repr.pi_b)?,
+            c: parse_g1(&interm_repr.pi_c)?,
         })
     }
 }
 
+impl ToJson for ZkProof {
+    fn to_json(&self) -> Result<Vec<u8>, Error> {
+        let interm_repr = ZkProofIntermidiateRepr {
+            pi_a: format_g1(&self.a),
+            pi_b: format_g2(&self.b),
+            pi_c: format_g1(&self.c),
+            protocol: Some(PROTOCOL.to_string()),
+            curve: Some(CURVE.to_string()),
+        };
+
+        Ok(serde_json::to_vec(&interm_repr)?)
+    }
+}
+
 impl FromJson for PublicInputs {
     type Output = PublicInputs;
 
     fn from_json(src: &[u8]) -> Result<Self::Output, Error> {
         let interm_repr: Vec<String> = serde_json::from_slice(src)?;
 
-        let mut result = Vec::new();
-        for value in interm_repr {
-            let scalar_bigint = BigUint::from_str(value.as_str())?;
+        interm_repr.iter().map(|value| parse_field(value)).collect()
+    }
+}
 
-            result.push(<Bn254 as Pairing>::ScalarField::from(scalar_bigint))
-        }
+impl ToJson for PublicInputs {
+    fn to_json(&self) -> Result<Vec<u8>, Error> {
+        let interm_repr: Vec<String> = self.iter().map(format_field).collect();
 
-        Ok(result)
+        Ok(serde_json::to_vec(&interm_repr)?)
     }
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct VerificationKeyIntermidiateRepr {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curve: Option<String>,
+    #[serde(rename = "nPublic", default, skip_serializing_if = "Option::is_none")]
+    pub n_public: Option<usize>,
     pub vk_alpha_1: [String; 3],
     pub vk_beta_2: [[String; 2]; 3],
     pub vk_gamma_2: [[String; 2]; 3],
@@ -127,88 +277,583 @@ impl FromJson for VerificationKey {
     fn from_json(src: &[u8]) -> Result<Self::Output, Error> {
         let interm_repr: VerificationKeyIntermidiateRepr = serde_json::from_slice(src)?;
 
-        let vk_alpha_1 = G1Affine::new(
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                interm_repr.vk_alpha_1[0].as_str(),
-            )?),
-            <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                interm_repr.vk_alpha_1[1].as_str(),
-            )?),
+        check_protocol_and_curve(&interm_repr.protocol, &interm_repr.curve)?;
+
+        if let Some(n_public) = interm_repr.n_public {
+            if n_public + 1 != interm_repr.ic.len() {
+                return Err(Error::PublicInputsNumberMismatch {
+                    expected: interm_repr.ic.len().saturating_sub(1),
+                    actual: n_public,
+                });
+            }
+        }
+
+        let gamma_abc_g1 = interm_repr
+            .ic
+            .iter()
+            .map(parse_g1)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(VerificationKey {
+            alpha_g1: parse_g1(&interm_repr.vk_alpha_1)?,
+            beta_g2: parse_g2(&interm_repr.vk_beta_2)?,
+            gamma_g2: parse_g2(&interm_repr.vk_gamma_2)?,
+            delta_g2: parse_g2(&interm_repr.vk_delta_2)?,
+            gamma_abc_g1,
+        })
+    }
+}
+
+impl ToJson for VerificationKey {
+    fn to_json(&self) -> Result<Vec<u8>, Error> {
+        let interm_repr = VerificationKeyIntermidiateRepr {
+            protocol: Some(PROTOCOL.to_string()),
+            curve: Some(CURVE.to_string()),
+            n_public: Some(self.gamma_abc_g1.len().saturating_sub(1)),
+            vk_alpha_1: format_g1(&self.alpha_g1),
+            vk_beta_2: format_g2(&self.beta_g2),
+            vk_gamma_2: format_g2(&self.gamma_g2),
+            vk_delta_2: format_g2(&self.delta_g2),
+            ic: self.gamma_abc_g1.iter().map(format_g1).collect(),
+        };
+
+        Ok(serde_json::to_vec(&interm_repr)?)
+    }
+}
+
+macro_rules! impl_bytes_encoding {
+    ($($ty:ty),*) => {$(
+        impl ToBytes for $ty {
+            fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+                let mut result = Vec::with_capacity(self.compressed_size());
+                self.serialize_compressed(&mut result)?;
+
+                Ok(result)
+            }
+        }
+
+        impl FromBytes for $ty {
+            type Output = $ty;
+
+            fn from_bytes(mut src: &[u8]) -> Result<Self::Output, Error> {
+                let result = <$ty>::deserialize_compressed(&mut src)?;
+                if !src.is_empty() {
+                    return Err(Error::TrailingBytes(src.len()));
+                }
+
+                Ok(result)
+            }
+        }
+    )*};
+}
+
+impl_bytes_encoding!(ZkProof, VerificationKey, PublicInputs);
+
+fn check_protocol_and_curve(
+    protocol: &Option<String>,
+    curve: &Option<String>,
+) -> Result<(), Error> {
+    if let Some(protocol) = protocol {
+        if protocol != PROTOCOL {
+            return Err(Error::UnsupportedProtocol(protocol.clone()));
+        }
+    }
+    if let Some(curve) = curve {
+        if curve != CURVE {
+            return Err(Error::UnsupportedCurve(curve.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+fn format_field<F: PrimeField>(value: &F) -> String {
+    let value: BigUint = value.into_bigint().into();
+
+    value.to_string()
+}
+
+fn format_fq2(value: &Fq2) -> [String; 2] {
+    [format_field(&value.c0), format_field(&value.c1)]
+}
+
+/// Formats a G1 point in the snarkjs projective `[x, y, z]` representation.
+fn format_g1(point: &G1Affine) -> [String; 3] {
+    match point.xy() {
+        Some((x, y)) => [format_field(x), format_field(y), "1".to_string()],
+        None => ["0".to_string(), "1".to_string(), "0".to_string()],
+    }
+}
+
+/// Formats a G2 point in the snarkjs projective `[x, y, z]` representation.
+fn format_g2(point: &G2Affine) -> [[String; 2]; 3] {
+    let (x, y, z) = match point.xy() {
+        Some((x, y)) => (*x, *y, Fq2::from(1u8)),
+        None => (Fq2::from(0u8), Fq2::from(1u8), Fq2::from(0u8)),
+    };
+
+    [format_fq2(&x), format_fq2(&y), format_fq2(&z)]
+}
+
+/// Parses a decimal string into a field element, rejecting non-canonical values, i.e. the ones
+/// that are greater or equal to the field modulus.
+fn parse_field<F: PrimeField<BigInt = BigInteger256>>(value: &str) -> Result<F, Error> {
+    let value_biguint = BigUint::from_str(value)?;
+
+    BigInteger256::try_from(value_biguint)
+        .ok()
+        .and_then(F::from_bigint)
+        .ok_or_else(|| Error::NotInField(value.to_string()))
+}
+
+fn parse_fq2(value: &[String; 2]) -> Result<Fq2, Error> {
+    Ok(Fq2::new(parse_field(&value[0])?, parse_field(&value[1])?))
+}
+
+/// Parses a G1 point from the snarkjs projective `[x, y, z]` representation. `z` has to be either
+/// one for an affine point or zero for the point at infinity.
+fn parse_g1(value: &[String; 3]) -> Result<G1Affine, Error> {
+    let z: Fq = parse_field(&value[2])?;
+
+    let point = if z == Fq::from(0u8) {
+        G1Affine::zero()
+    } else if z == Fq::from(1u8) {
+        G1Affine::new_unchecked(parse_field(&value[0])?, parse_field(&value[1])?)
+    } else {
+        return Err(Error::InvalidProjectiveZ);
+    };
+
+    check_point(point)
+}
+
+/// Parses a G2 point from the snarkjs projective `[x, y, z]` representation. `z` has to be either
+/// one for an affine point or zero for the point at infinity.
+fn parse_g2(value: &[[String; 2]; 3]) -> Result<G2Affine, Error> {
+    let z = parse_fq2(&value[2])?;
+
+    let point = if z == Fq2::from(0u8) {
+        G2Affine::zero()
+    } else if z == Fq2::from(1u8) {
+        G2Affine::new_unchecked(parse_fq2(&value[0])?, parse_fq2(&value[1])?)
+    } else {
+        return Err(Error::InvalidProjectiveZ);
+    };
+
+    check_point(point)
+}
+
+fn check_point<P: SWCurveConfig>(point: Affine<P>) -> Result<Affine<P>, Error> {
+    if point.is_zero() {
+        return Ok(point);
+    }
+
+    if !point.is_on_curve() {
+        return Err(Error::PointNotOnCurve);
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::PointNotInSubgroup);
+    }
+
+    Ok(point)
+}
+
+#[cfg(test)]
+mod test {
+    use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::{BigInteger256, PrimeField};
+    use ark_groth16::Groth16;
+    use ark_std::test_rng;
+    use num::BigUint;
+
+    use super::{
+        format_g2, groth16_verifier, parse_g1, parse_g2, Error, FromBytes, FromJson, PublicInputs,
+        ToBytes, ToJson, VerificationKey, Verifier, ZkProof,
+    };
+
+    /// A snarkjs proof with a single public input from the risc0-groth16 test data.
+    const PROOF_JSON: &str = include_str!("../tests/data/proof.json");
+    const PUBLIC_JSON: &str = include_str!("../tests/data/public.json");
+    const VERIFICATION_KEY_JSON: &str = include_str!("../tests/data/verification_key.json");
+
+    fn strings<const N: usize>(values: [&str; N]) -> [String; N] {
+        values.map(str::to_string)
+    }
+
+    fn proof_value() -> serde_json::Value {
+        serde_json::from_str(PROOF_JSON).unwrap()
+    }
+
+    fn modulus<F: PrimeField<BigInt = BigInteger256>>() -> String {
+        BigUint::from(F::MODULUS).to_string()
+    }
+
+    #[test]
+    fn test_verify_snarkjs_proof() {
+        assert!(groth16_verifier(
+            VERIFICATION_KEY_JSON.as_bytes(),
+            PROOF_JSON.as_bytes(),
+            PUBLIC_JSON.as_bytes(),
+        )
+        .unwrap());
+
+        let verifier = Verifier::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        let mut public_inputs = PublicInputs::from_json(PUBLIC_JSON.as_bytes()).unwrap();
+        assert!(verifier.verify(&proof, &public_inputs).unwrap());
+
+        public_inputs[0] += Fr::from(1u8);
+        assert!(!verifier.verify(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn test_point_not_on_curve() {
+        let mut proof = proof_value();
+        proof["pi_a"][1] = "1".into();
+
+        assert!(matches!(
+            ZkProof::from_json(proof.to_string().as_bytes()),
+            Err(Error::PointNotOnCurve)
+        ));
+
+        let mut proof = proof_value();
+        proof["pi_b"][1][0] = "1".into();
+
+        assert!(matches!(
+            ZkProof::from_json(proof.to_string().as_bytes()),
+            Err(Error::PointNotOnCurve)
+        ));
+    }
+
+    #[test]
+    fn test_g2_point_not_in_subgroup() {
+        // The points on the twist that aren't multiplied by the cofactor are outside the subgroup.
+        let point = (1u64..)
+            .find_map(|x| G2Affine::get_point_from_x_unchecked(Fq2::from(x), false))
+            .unwrap();
+        assert!(point.is_on_curve());
+        assert!(!point.is_in_correct_subgroup_assuming_on_curve());
+
+        assert!(matches!(
+            parse_g2(&format_g2(&point)),
+            Err(Error::PointNotInSubgroup)
+        ));
+
+        let mut proof = proof_value();
+        proof["pi_b"] = serde_json::to_value(format_g2(&point)).unwrap();
+
+        assert!(matches!(
+            ZkProof::from_json(proof.to_string().as_bytes()),
+            Err(Error::PointNotInSubgroup)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_projective_z() {
+        assert!(matches!(
+            parse_g1(&strings(["1", "2", "2"])),
+            Err(Error::InvalidProjectiveZ)
+        ));
+        assert!(matches!(
+            parse_g2(&[
+                strings(["1", "0"]),
+                strings(["2", "0"]),
+                strings(["1", "1"])
+            ]),
+            Err(Error::InvalidProjectiveZ)
+        ));
+
+        // The point at infinity and the affine generator are accepted.
+        assert!(parse_g1(&strings(["0", "1", "0"])).unwrap().is_zero());
+        assert!(parse_g1(&strings(["1", "2", "1"])).is_ok());
+    }
+
+    #[test]
+    fn test_non_canonical_field_element() {
+        // `1 + p` is `1` modulo `p`, but it isn't the canonical representation.
+        let one_plus_p = (BigUint::from(1u8) + BigUint::from(Fq::MODULUS)).to_string();
+        assert!(matches!(
+            parse_g1(&[one_plus_p, "2".to_string(), "1".to_string()]),
+            Err(Error::NotInField(_))
+        ));
+
+        let mut proof = proof_value();
+        proof["pi_c"][0] = modulus::<Fq>().into();
+        assert!(matches!(
+            ZkProof::from_json(proof.to_string().as_bytes()),
+            Err(Error::NotInField(_))
+        ));
+
+        let public_inputs = serde_json::json!([modulus::<Fr>()]).to_string();
+        assert!(matches!(
+            PublicInputs::from_json(public_inputs.as_bytes()),
+            Err(Error::NotInField(_))
+        ));
+    }
+
+    #[test]
+    fn test_non_numeric_value() {
+        assert!(matches!(
+            parse_g1(&strings(["0x1", "2", "1"])),
+            Err(Error::ParsePoints(_))
+        ));
+        assert!(matches!(
+            parse_g1(&strings(["1", "-2", "1"])),
+            Err(Error::ParsePoints(_))
+        ));
+        assert!(matches!(
+            PublicInputs::from_json(br#"["one"]"#),
+            Err(Error::ParsePoints(_))
+        ));
+    }
+
+    #[test]
+    fn test_public_inputs_number_mismatch() {
+        let mut verification_key: serde_json::Value =
+            serde_json::from_str(VERIFICATION_KEY_JSON).unwrap();
+        verification_key["nPublic"] = 2.into();
+
+        assert!(matches!(
+            VerificationKey::from_json(verification_key.to_string().as_bytes()),
+            Err(Error::PublicInputsNumberMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+
+        let verifier = Verifier::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+
+        assert!(matches!(
+            verifier.verify(&proof, &[Fr::from(1u8), Fr::from(2u8)]),
+            Err(Error::PublicInputsNumberMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            verifier.verify(&proof, &[]),
+            Err(Error::PublicInputsNumberMismatch {
+                expected: 1,
+                actual: 0
+            })
+        ));
+    }
+
+    fn batch(size: usize) -> (Verifier, Vec<(ZkProof, PublicInputs)>) {
+        let verifier = Verifier::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        let public_inputs = PublicInputs::from_json(PUBLIC_JSON.as_bytes()).unwrap();
+
+        // Rerandomized proofs are different valid proofs of the same statement.
+        let mut rng = test_rng();
+        let proofs = (0..size)
+            .map(|_| {
+                let proof = Groth16::<Bn254>::rerandomize_proof(
+                    verifier.verification_key(),
+                    &proof,
+                    &mut rng,
+                );
+                (proof, public_inputs.clone())
+            })
+            .collect();
+
+        (verifier, proofs)
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let (verifier, proofs) = batch(3);
+        assert_ne!(proofs[0].0, proofs[1].0);
+
+        assert!(verifier.verify_batch(&proofs, &mut test_rng()).unwrap());
+    }
+
+    #[test]
+    fn test_verify_batch_with_invalid_proof() {
+        let (verifier, mut proofs) = batch(3);
+        proofs[1].0.c = (proofs[1].0.c + G1Affine::generator()).into_affine();
+        assert!(!verifier.verify_batch(&proofs, &mut test_rng()).unwrap());
+
+        let (verifier, mut proofs) = batch(3);
+        proofs[2].1[0] += Fr::from(1u8);
+        assert!(!verifier.verify_batch(&proofs, &mut test_rng()).unwrap());
+
+        let (verifier, mut proofs) = batch(3);
+        proofs[0].1.push(Fr::from(1u8));
+        assert!(matches!(
+            verifier.verify_batch(&proofs, &mut test_rng()),
+            Err(Error::PublicInputsNumberMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_verify_batch_of_one() {
+        let (verifier, mut proofs) = batch(1);
+        let (proof, public_inputs) = &proofs[0];
+        assert!(verifier.verify(proof, public_inputs).unwrap());
+        assert!(verifier.verify_batch(&proofs, &mut test_rng()).unwrap());
+
+        proofs[0].1[0] += Fr::from(1u8);
+        let (proof, public_inputs) = &proofs[0];
+        assert!(!verifier.verify(proof, public_inputs).unwrap());
+        assert!(!verifier.verify_batch(&proofs, &mut test_rng()).unwrap());
+    }
+
+    #[test]
+    fn test_verify_empty_batch() {
+        let (verifier, _) = batch(0);
+
+        assert!(verifier.verify_batch(&[], &mut test_rng()).unwrap());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        let proof_json = proof.to_json().unwrap();
+        assert_eq!(ZkProof::from_json(&proof_json).unwrap(), proof);
+        assert_eq!(
+            ZkProof::from_json(&proof_json).unwrap().to_json().unwrap(),
+            proof_json
         );
 
-        let vk_beta_2 = G2Affine::new(
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_beta_2[0][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_beta_2[0][1].as_str(),
-                )?),
-            },
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_beta_2[1][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_beta_2[1][1].as_str(),
-                )?),
-            },
+        let verification_key =
+            VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        let verification_key_json = verification_key.to_json().unwrap();
+        assert_eq!(
+            VerificationKey::from_json(&verification_key_json).unwrap(),
+            verification_key
+        );
+        assert_eq!(
+            VerificationKey::from_json(&verification_key_json)
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            verification_key_json
         );
 
-        let vk_gamma_2 = G2Affine::new(
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_gamma_2[0][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_gamma_2[0][1].as_str(),
-                )?),
-            },
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_gamma_2[1][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_gamma_2[1][1].as_str(),
-                )?),
-            },
+        let public_inputs = PublicInputs::from_json(PUBLIC_JSON.as_bytes()).unwrap();
+        let public_inputs_json = public_inputs.to_json().unwrap();
+        assert_eq!(
+            PublicInputs::from_json(&public_inputs_json).unwrap(),
+            public_inputs
         );
+        assert_eq!(public_inputs_json, br#"["33"]"#);
+    }
 
-        let vk_delta_2 = G2Affine::new(
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_delta_2[0][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_delta_2[0][1].as_str(),
-                )?),
-            },
-            QuadExtField {
-                c0: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_delta_2[1][0].as_str(),
-                )?),
-                c1: <Bn254 as Pairing>::BaseField::from(BigUint::from_str(
-                    interm_repr.vk_delta_2[1][1].as_str(),
-                )?),
-            },
+    #[test]
+    fn test_bytes_round_trip() {
+        let proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        let proof_bytes = proof.to_bytes().unwrap();
+        // Two compressed G1 points and a compressed G2 point, as the `ToBytes` docs promise.
+        assert_eq!(proof_bytes.len(), 128);
+        assert_eq!(ZkProof::from_bytes(&proof_bytes).unwrap(), proof);
+        assert_eq!(
+            ZkProof::from_bytes(&proof_bytes)
+                .unwrap()
+                .to_bytes()
+                .unwrap(),
+            proof_bytes
         );
 
-        let mut gamma_abc_g1 = Vec::new();
-        for val in interm_repr.ic.iter() {
-            let ic = G1Affine::new(
-                <Bn254 as Pairing>::BaseField::from(BigUint::from_str(val[0].as_str())?),
-                <Bn254 as Pairing>::BaseField::from(BigUint::from_str(val[1].as_str())?),
-            );
+        let verification_key =
+            VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes()).unwrap();
+        let verification_key_bytes = verification_key.to_bytes().unwrap();
+        assert_eq!(
+            VerificationKey::from_bytes(&verification_key_bytes).unwrap(),
+            verification_key
+        );
+        assert_eq!(
+            VerificationKey::from_bytes(&verification_key_bytes)
+                .unwrap()
+                .to_bytes()
+                .unwrap(),
+            verification_key_bytes
+        );
 
-            gamma_abc_g1.push(ic);
-        }
+        let public_inputs = PublicInputs::from_json(PUBLIC_JSON.as_bytes()).unwrap();
+        let public_inputs_bytes = public_inputs.to_bytes().unwrap();
+        // The length prefix and a field element.
+        assert_eq!(public_inputs_bytes.len(), 8 + 32);
+        assert_eq!(
+            PublicInputs::from_bytes(&public_inputs_bytes).unwrap(),
+            public_inputs
+        );
+    }
 
-        Ok(VerificationKey {
-            alpha_g1: vk_alpha_1,
-            beta_g2: vk_beta_2,
-            gamma_g2: vk_gamma_2,
-            delta_g2: vk_delta_2,
-            gamma_abc_g1,
-        })
+    #[test]
+    fn test_bytes_rejects_truncated_and_trailing_bytes() {
+        let proof_bytes = ZkProof::from_json(PROOF_JSON.as_bytes())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert!(matches!(
+            ZkProof::from_bytes(&proof_bytes[..proof_bytes.len() - 1]),
+            Err(Error::Binary(_))
+        ));
+        assert!(matches!(
+            ZkProof::from_bytes(&[proof_bytes.as_slice(), &[0]].concat()),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert!(matches!(ZkProof::from_bytes(&[]), Err(Error::Binary(_))));
+
+        let verification_key_bytes = VerificationKey::from_json(VERIFICATION_KEY_JSON.as_bytes())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert!(matches!(
+            VerificationKey::from_bytes(
+                &verification_key_bytes[..verification_key_bytes.len() - 32]
+            ),
+            Err(Error::Binary(_))
+        ));
+
+        let public_inputs_bytes = PublicInputs::from_json(PUBLIC_JSON.as_bytes())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert!(matches!(
+            PublicInputs::from_bytes(&public_inputs_bytes[..public_inputs_bytes.len() - 1]),
+            Err(Error::Binary(_))
+        ));
+    }
+
+    #[test]
+    fn test_bytes_rejects_non_canonical_values() {
+        // The compressed point is the little-endian `x` with the flags in the top bits, so `x = q`
+        // with no flags set is out of the base field.
+        let mut proof_bytes = ZkProof::from_json(PROOF_JSON.as_bytes())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        proof_bytes[..32].copy_from_slice(&BigUint::from(Fq::MODULUS).to_bytes_le());
+        assert!(matches!(
+            ZkProof::from_bytes(&proof_bytes),
+            Err(Error::Binary(_))
+        ));
+
+        let mut public_inputs_bytes = PublicInputs::from_json(PUBLIC_JSON.as_bytes())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        public_inputs_bytes[8..].copy_from_slice(&BigUint::from(Fr::MODULUS).to_bytes_le());
+        assert!(matches!(
+            PublicInputs::from_bytes(&public_inputs_bytes),
+            Err(Error::Binary(_))
+        ));
+
+        // A G2 point outside the subgroup is rejected as it is in JSON.
+        let point = (1u64..)
+            .find_map(|x| G2Affine::get_point_from_x_unchecked(Fq2::from(x), false))
+            .unwrap();
+        let mut proof = ZkProof::from_json(PROOF_JSON.as_bytes()).unwrap();
+        proof.b = point;
+        assert!(matches!(
+            ZkProof::from_bytes(&proof.to_bytes().unwrap()),
+            Err(Error::Binary(_))
+        ));
     }
 }
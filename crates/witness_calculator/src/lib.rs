@@ -1,23 +1,132 @@
-use eyre::Result;
+use eyre::{eyre, Result};
 use fnv::FnvHasher;
-use num::BigInt;
-use num::ToPrimitive;
-use num_traits::Zero;
+use num::{BigInt, BigUint};
+use std::collections::HashSet;
 use std::hash::Hasher;
-use std::io::Write;
-use wasmer::{imports, Function, Instance, Memory, MemoryType, Module, RuntimeError, Store};
+use wasmer::{imports, FunctionEnv, Instance, Memory, MemoryType, Module, RuntimeError, Store};
 
+mod runtime;
 mod wasm;
+mod witness;
+use crate::runtime::RuntimeEnv;
 use crate::wasm::{CircomWasm, Wasm};
 
-#[derive(thiserror::Error, Debug, Clone, Copy)]
-#[error("{0}")]
-struct ExitCode(u32);
+pub use witness::{Witness, WtnsError};
+
+/// The reason the witness calculator has stopped, as reported by the Circom runtime.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WitnessErrorKind {
+    #[error("signal not found")]
+    SignalNotFound,
+    #[error("too many signals set")]
+    TooManySignalsSet,
+    #[error("signal already set")]
+    SignalAlreadySet,
+    #[error("assert failed")]
+    AssertFailed,
+    #[error("not enough memory")]
+    NotEnoughMemory,
+    #[error("input signal array access exceeds the size")]
+    InputSignalOutOfBounds,
+    #[error("unknown error with code {0}")]
+    Unknown(i32),
+}
+
+impl WitnessErrorKind {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            1 => Self::SignalNotFound,
+            2 => Self::TooManySignalsSet,
+            3 => Self::SignalAlreadySet,
+            4 => Self::AssertFailed,
+            5 => Self::NotEnoughMemory,
+            6 => Self::InputSignalOutOfBounds,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+/// Error raised by the circuit while the witness was being calculated, e.g. a failed assert.
+#[derive(Debug, Clone)]
+pub struct WitnessError {
+    pub kind: WitnessErrorKind,
+
+    /// The message printed by the circuit, e.g. `Error in template AtomicSwap_0 line: 12`.
+    pub message: String,
+
+    /// The template the error came from, if the message names it.
+    pub template: Option<String>,
+
+    /// The line of the template the error came from, if the message names it.
+    pub line: Option<u32>,
+
+    /// `log()` output of the circuit collected before the error.
+    pub logs: Vec<String>,
+}
+
+impl WitnessError {
+    pub fn new(kind: WitnessErrorKind, message: String, logs: Vec<String>) -> Self {
+        let (template, line) = parse_template_location(&message);
+
+        Self {
+            kind,
+            message,
+            template,
+            line,
+            logs,
+        }
+    }
+}
+
+impl std::fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(template) = &self.template {
+            write!(f, " in template {template}")?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " at line {line}")?;
+        }
+        if self.template.is_none() && !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+/// Error returned when the inputs don't match the circuit's input signals. It is checked before
+/// the witness calculation starts.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    #[error("input signal `{0}` not found in the circuit")]
+    UnknownSignal(String),
+    #[error("input signal `{0}` is set more than once")]
+    DuplicatedSignal(String),
+    #[error("input signal `{name}` expects {expected} values, got {actual}")]
+    InvalidLength {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("value #{index} of input signal `{name}` is outside the field: {value}")]
+    ValueOutOfField {
+        name: String,
+        index: usize,
+        value: BigInt,
+    },
+    #[error("not all input signals are set: {actual} out of {expected}")]
+    MissingInputs { expected: usize, actual: usize },
+}
 
 #[derive(Debug)]
 pub struct WitnessCalculator {
     pub instance: Wasm,
     pub store: Store,
+    runtime: FunctionEnv<RuntimeEnv>,
 }
 
 impl WitnessCalculator {
@@ -32,46 +141,92 @@ impl WitnessCalculator {
         Self::from_module(module, store)
     }
 
+    /// Compiles the witness calculator from the `.wasm` bytes, e.g. embedded into the binary.
+    pub fn from_bytes(wasm: &[u8]) -> Result<Self> {
+        let store = Store::default();
+        let module = Module::new(&store, wasm)?;
+
+        Self::from_module(module, store)
+    }
+
     pub fn from_module(module: Module, mut store: Store) -> Result<Self> {
+        // Circom 1.x modules don't have the Circom 2.x API, see `runtime`.
+        if !module
+            .exports()
+            .any(|export| export.name() == "getFieldNumLen32")
+        {
+            return Err(eyre!("only Circom 2.x witness calculators are supported"));
+        }
+
         let memory = Memory::new(&mut store, MemoryType::new(2000, None, false)).unwrap();
+        let env = FunctionEnv::new(&mut store, RuntimeEnv::default());
         let import_object = imports! {
             "env" => {
                 "memory" => memory.clone(),
             },
             "runtime" => {
-                "error" => runtime::error(&mut store),
-                "logSetSignal" => runtime::log_signal(&mut store),
-                "logGetSignal" => runtime::log_signal(&mut store),
-                "logFinishComponent" => runtime::log_component(&mut store),
-                "logStartComponent" => runtime::log_component(&mut store),
-                "log" => runtime::log_component(&mut store),
-                "exceptionHandler" => runtime::exception_handler(&mut store),
-                "showSharedRWMemory" => runtime::show_memory(&mut store),
-                "printErrorMessage" => runtime::print_error_message(&mut store),
-                "writeBufferMessage" => runtime::write_buffer_message(&mut store),
+                "exceptionHandler" => runtime::exception_handler(&mut store, &env),
+                "showSharedRWMemory" => runtime::show_memory(&mut store, &env),
+                "printErrorMessage" => runtime::print_error_message(&mut store, &env),
+                "writeBufferMessage" => runtime::write_buffer_message(&mut store, &env),
             }
         };
 
-        let instance = Wasm::new(Instance::new(&mut store, &module, &import_object)?);
+        let instance = Instance::new(&mut store, &module, &import_object)?;
+        env.as_mut(&mut store).instance = Some(instance.clone());
+
+        Ok(WitnessCalculator {
+            instance: Wasm::new(instance),
+            store,
+            runtime: env,
+        })
+    }
 
-        Ok(WitnessCalculator { instance, store })
+    /// Returns the `log()` output of the circuit collected during the last
+    /// [`Self::calculate_witness`] call.
+    pub fn logs(&self) -> &[String] {
+        &self.runtime.as_ref(&self.store).logs
     }
 
+    /// Calculates the witness for the given inputs. Use [`Witness::to_wtns_bytes`] to get it in
+    /// the format the prover expects.
+    ///
+    /// The inputs are checked against the circuit's input signals first: every value must be in
+    /// `[0, p)`, where `p` is the circuit's prime. If they don't match, the returned error can be
+    /// downcast to [`InputError`]. If the circuit fails, e.g. on an assert, the returned error can
+    /// be downcast to [`WitnessError`].
     pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
         &mut self,
         inputs: I,
         sanity_check: bool,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<Witness> {
+        self.runtime.as_mut(&mut self.store).reset();
+
+        self.calculate_witness_inner(inputs, sanity_check)
+            .map_err(|err| match err.downcast::<RuntimeError>() {
+                Ok(err) => match err.downcast::<WitnessError>() {
+                    Ok(err) => err.into(),
+                    Err(err) => err.into(),
+                },
+                Err(err) => err,
+            })
+    }
+
+    fn calculate_witness_inner<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Witness> {
         self.instance.init(sanity_check, &mut self.store)?;
 
         let n32 = self.instance.get_field_num_len32(&mut self.store)?;
 
-        // allocate the inputs
-        for (name, values) in inputs.into_iter() {
-            let (msb, lsb) = fnv(&name);
+        let inputs = self.validate_inputs(inputs)?;
 
-            for (i, value) in values.into_iter().enumerate() {
-                let f_arr = to_array32(&value, n32 as usize);
+        // allocate the inputs
+        for (msb, lsb, values) in inputs {
+            for (i, value) in values.iter().enumerate() {
+                let f_arr = to_array32(value, n32 as usize);
                 for j in 0..n32 {
                     self.instance.write_shared_rw_memory(
                         j,
@@ -84,119 +239,101 @@ impl WitnessCalculator {
             }
         }
 
-        let mut binary_witnes = Vec::new();
-
-        // wtns
-        binary_witnes.write_all(&[b'w', b't', b'n', b's']).unwrap();
-
-        // version - 2
-        binary_witnes.write_all(&u32::to_le_bytes(2)).unwrap();
-
-        // number of sections - 2
-        binary_witnes.write_all(&u32::to_le_bytes(2)).unwrap();
-
-        // id section - 1
-        binary_witnes.write_all(&u32::to_le_bytes(1)).unwrap();
-
-        let n8 = n32 * 4;
-
-        // id section 1 length in 64bytes
-        binary_witnes
-            .write_all(&u64::to_le_bytes((n8 + 8) as u64))
-            .unwrap();
-
-        // n8
-        binary_witnes.write_all(&u32::to_le_bytes(n8)).unwrap();
-
-        // prime number
-        self.instance.get_raw_prime(&mut self.store)?;
-
-        for i in 0..n32 {
-            let val = self.instance.read_shared_rw_memory(i, &mut self.store)?;
-            binary_witnes.write_all(&u32::to_le_bytes(val)).unwrap();
-        }
-
-        // witness size
+        let prime = self.instance.get_prime(&mut self.store)?;
         let witness_size = self.instance.get_witness_size(&mut self.store)?;
-        binary_witnes
-            .write_all(&u32::to_le_bytes(witness_size))
-            .unwrap();
-
-        // id section - 2
-        binary_witnes.write_all(&u32::to_le_bytes(2)).unwrap();
-
-        // id section 2 length in 64bytes
-        binary_witnes
-            .write_all(&u64::to_le_bytes((n8 * witness_size) as u64))
-            .unwrap();
 
+        let mut values = Vec::with_capacity(witness_size as usize);
         for i in 0..witness_size {
             self.instance.get_witness(i, &mut self.store)?;
 
+            let mut digits = Vec::with_capacity(n32 as usize);
             for j in 0..n32 {
-                let val = self.instance.read_shared_rw_memory(j, &mut self.store)?;
-                binary_witnes
-                    .write_all(&u32::to_le_bytes(val as i32 as u32))
-                    .unwrap();
+                digits.push(self.instance.read_shared_rw_memory(j, &mut self.store)?);
             }
+            values.push(BigUint::new(digits));
         }
 
-        Ok(binary_witnes)
+        Ok(Witness { prime, values })
     }
 }
 
-mod runtime {
-    use super::*;
+impl WitnessCalculator {
+    /// Checks the inputs against the circuit's input signal table and returns them with the
+    /// hashes of their names.
+    fn validate_inputs<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+    ) -> Result<Vec<(u32, u32, Vec<BigUint>)>> {
+        let prime = self.instance.get_prime(&mut self.store)?;
 
-    pub fn error(store: &mut Store) -> Function {
-        #[allow(unused)]
-        #[allow(clippy::many_single_char_names)]
-        fn func(a: i32, b: i32, c: i32, d: i32, e: i32, f: i32) -> Result<(), RuntimeError> {
-            println!("runtime error, exiting early: {a} {b} {c} {d} {e} {f}",);
-            Err(RuntimeError::user(Box::new(ExitCode(1))))
-        }
-        Function::new_typed(store, func)
-    }
+        let mut validated = Vec::new();
+        let mut hashes = HashSet::new();
+        let mut inputs_number = 0;
+        for (name, values) in inputs.into_iter() {
+            let (msb, lsb) = fnv(&name);
+            // The circuit only knows the hashes, so the same signal can't be counted twice.
+            if !hashes.insert((msb, lsb)) {
+                return Err(InputError::DuplicatedSignal(name).into());
+            }
 
-    // Circom 2.0
-    pub fn exception_handler(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func(a: i32) {}
-        Function::new_typed(store, func)
-    }
+            let signal_size = self
+                .instance
+                .get_input_signal_size(msb, lsb, &mut self.store)?;
+            let Ok(expected) = usize::try_from(signal_size) else {
+                return Err(InputError::UnknownSignal(name).into());
+            };
+
+            if values.len() != expected {
+                return Err(InputError::InvalidLength {
+                    name,
+                    expected,
+                    actual: values.len(),
+                }
+                .into());
+            }
 
-    // Circom 2.0
-    pub fn show_memory(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func() {}
-        Function::new_typed(store, func)
-    }
+            let mut field_values = Vec::with_capacity(values.len());
+            for (index, value) in values.into_iter().enumerate() {
+                match value.to_biguint() {
+                    Some(field_value) if field_value < prime => field_values.push(field_value),
+                    _ => {
+                        return Err(InputError::ValueOutOfField { name, index, value }.into());
+                    }
+                }
+            }
 
-    // Circom 2.0
-    pub fn print_error_message(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func() {}
-        Function::new_typed(store, func)
-    }
+            inputs_number += expected;
+            validated.push((msb, lsb, field_values));
+        }
 
-    // Circom 2.0
-    pub fn write_buffer_message(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func() {}
-        Function::new_typed(store, func)
-    }
+        let expected_inputs_number = self.instance.get_input_size(&mut self.store)? as usize;
+        if inputs_number < expected_inputs_number {
+            return Err(InputError::MissingInputs {
+                expected: expected_inputs_number,
+                actual: inputs_number,
+            }
+            .into());
+        }
 
-    pub fn log_signal(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func(a: i32, b: i32) {}
-        Function::new_typed(store, func)
+        Ok(validated)
     }
+}
 
-    pub fn log_component(store: &mut Store) -> Function {
-        #[allow(unused)]
-        fn func(a: i32) {}
-        Function::new_typed(store, func)
-    }
+/// Extracts the template name and line from a Circom error message such as
+/// `Error in template AtomicSwap_0 line: 12`.
+fn parse_template_location(message: &str) -> (Option<String>, Option<u32>) {
+    let Some((_, location)) = message.split_once("Error in template ") else {
+        return (None, None);
+    };
+
+    let mut words = location.split_whitespace();
+    let template = words.next().map(str::to_string);
+    let line = match (words.next(), words.next()) {
+        (Some("line:"), Some(line)) => line.parse().ok(),
+        _ => None,
+    };
+
+    (template, line)
 }
 
 fn fnv(inp: &str) -> (u32, u32) {
@@ -207,16 +344,97 @@ fn fnv(inp: &str) -> (u32, u32) {
     ((h >> 32) as u32, h as u32)
 }
 
-fn to_array32(s: &BigInt, size: usize) -> Vec<u32> {
+fn to_array32(s: &BigUint, size: usize) -> Vec<u32> {
     let mut res = vec![0; size];
-    let mut rem = s.clone();
-    let radix = BigInt::from(0x100000000u64);
-    let mut c = size;
-    while !rem.is_zero() {
-        c -= 1;
-        res[c] = (&rem % &radix).to_u32().unwrap();
-        rem /= &radix;
+    for (i, digit) in s.iter_u32_digits().take(size).enumerate() {
+        res[size - 1 - i] = digit;
     }
 
     res
 }
+
+#[cfg(test)]
+mod test {
+    use num::BigInt;
+
+    use super::{InputError, WitnessCalculator};
+
+    const MOCK_CIRCUIT: &str = include_str!("../tests/data/mock_circuit.wat");
+
+    fn input_error(inputs: Vec<(&str, Vec<i64>)>) -> InputError {
+        let mut calculator = WitnessCalculator::from_bytes(MOCK_CIRCUIT.as_bytes()).unwrap();
+
+        let inputs = inputs.into_iter().map(|(name, values)| {
+            (
+                name.to_string(),
+                values.into_iter().map(BigInt::from).collect(),
+            )
+        });
+
+        calculator
+            .calculate_witness(inputs, false)
+            .unwrap_err()
+            .downcast::<InputError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unknown_signal() {
+        assert_eq!(
+            input_error(vec![("a", vec![1]), ("b", vec![2, 3]), ("c", vec![4])]),
+            InputError::UnknownSignal("c".to_string())
+        );
+    }
+
+    #[test]
+    fn test_duplicated_signal() {
+        assert_eq!(
+            input_error(vec![("a", vec![1]), ("a", vec![2]), ("b", vec![3, 4])]),
+            InputError::DuplicatedSignal("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert_eq!(
+            input_error(vec![("a", vec![1]), ("b", vec![2])]),
+            InputError::InvalidLength {
+                name: "b".to_string(),
+                expected: 2,
+                actual: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_value_out_of_field() {
+        // The mock circuit's prime is 2^31 - 1.
+        assert_eq!(
+            input_error(vec![("a", vec![1]), ("b", vec![2, 2147483647])]),
+            InputError::ValueOutOfField {
+                name: "b".to_string(),
+                index: 1,
+                value: 2147483647.into(),
+            }
+        );
+        assert_eq!(
+            input_error(vec![("a", vec![-1]), ("b", vec![2, 3])]),
+            InputError::ValueOutOfField {
+                name: "a".to_string(),
+                index: 0,
+                value: (-1).into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_inputs() {
+        assert_eq!(
+            input_error(vec![("b", vec![2, 3])]),
+            InputError::MissingInputs {
+                expected: 3,
+                actual: 2,
+            }
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Host functions imported by the Circom witness calculator module under the `runtime`
+//! namespace.
+//!
+//! Circom 2.x reports failed asserts and `log()` calls through a shared message buffer: the
+//! module writes a message, calls one of the imports and the host reads it back char by char
+//! with the `getMessageChar` export. [`RuntimeEnv`] keeps the buffered output between those calls.
+//!
+//! Circom 1.x modules aren't supported: their `error` and `log` imports pass pointers to field
+//! elements in the module's own memory layout, and they don't export the Circom 2.x API the
+//! calculator relies on.
+
+use num::BigUint;
+use wasmer::{Function, FunctionEnv, FunctionEnvMut, Instance, RuntimeError, Store};
+
+use crate::{WitnessError, WitnessErrorKind};
+
+/// The state shared between the witness calculator and its `runtime` imports.
+#[derive(Debug, Default)]
+pub struct RuntimeEnv {
+    /// The instantiated module. It is [`None`] only while the module is being instantiated.
+    pub instance: Option<Instance>,
+
+    /// Messages passed to `printErrorMessage` since the last calculation has started.
+    pub error_message: String,
+
+    /// The `log()` line that is being built by `writeBufferMessage` and `showSharedRWMemory`.
+    pub log_line: String,
+
+    /// Completed `log()` lines since the last calculation has started.
+    pub logs: Vec<String>,
+}
+
+impl RuntimeEnv {
+    /// Drops the output of the previous calculation.
+    pub fn reset(&mut self) {
+        self.error_message.clear();
+        self.log_line.clear();
+        self.logs.clear();
+    }
+
+    fn push_log_item(&mut self, item: &str) {
+        if !self.log_line.is_empty() {
+            self.log_line.push(' ');
+        }
+        self.log_line.push_str(item);
+    }
+
+    fn flush_log_line(&mut self) {
+        if !self.log_line.is_empty() {
+            self.logs.push(std::mem::take(&mut self.log_line));
+        }
+    }
+
+    fn witness_error(&mut self, kind: WitnessErrorKind) -> RuntimeError {
+        self.flush_log_line();
+
+        RuntimeError::user(Box::new(WitnessError::new(
+            kind,
+            self.error_message.trim_end().to_string(),
+            self.logs.clone(),
+        )))
+    }
+}
+
+fn export(env: &FunctionEnvMut<RuntimeEnv>, name: &str) -> Result<Function, RuntimeError> {
+    let instance = env
+        .data()
+        .instance
+        .as_ref()
+        .ok_or_else(|| RuntimeError::new("witness calculator is not instantiated"))?;
+
+    instance
+        .exports
+        .get_function(name)
+        .cloned()
+        .map_err(|err| RuntimeError::new(format!("function {name} not found: {err}")))
+}
+
+fn call_u32(
+    env: &mut FunctionEnvMut<RuntimeEnv>,
+    func: &Function,
+    args: &[wasmer::Value],
+) -> Result<u32, RuntimeError> {
+    let result = func.call(env, args)?;
+
+    Ok(result[0].unwrap_i32() as u32)
+}
+
+/// Reads the message the module has put in its shared message buffer.
+fn read_message(env: &mut FunctionEnvMut<RuntimeEnv>) -> Result<String, RuntimeError> {
+    let get_message_char = export(env, "getMessageChar")?;
+
+    let mut message = String::new();
+    loop {
+        let c = call_u32(env, &get_message_char, &[])?;
+        if c == 0 {
+            break;
+        }
+        message.push(char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+
+    Ok(message)
+}
+
+/// Reads the field element the module has put in its shared RW memory.
+fn read_shared_rw_memory(env: &mut FunctionEnvMut<RuntimeEnv>) -> Result<BigUint, RuntimeError> {
+    let get_field_num_len32 = export(env, "getFieldNumLen32")?;
+    let read_shared_rw_memory = export(env, "readSharedRWMemory")?;
+
+    let n32 = call_u32(env, &get_field_num_len32, &[])?;
+
+    let mut digits = Vec::with_capacity(n32 as usize);
+    for i in 0..n32 {
+        digits.push(call_u32(env, &read_shared_rw_memory, &[i.into()])?);
+    }
+
+    // Shared RW memory is little-endian in 32-bit words.
+    Ok(BigUint::new(digits))
+}
+
+// Circom 2.0
+pub fn exception_handler(store: &mut Store, env: &FunctionEnv<RuntimeEnv>) -> Function {
+    fn func(mut env: FunctionEnvMut<RuntimeEnv>, code: i32) -> Result<(), RuntimeError> {
+        Err(env
+            .data_mut()
+            .witness_error(WitnessErrorKind::from_code(code)))
+    }
+    Function::new_typed_with_env(store, env, func)
+}
+
+// Circom 2.0
+pub fn show_memory(store: &mut Store, env: &FunctionEnv<RuntimeEnv>) -> Function {
+    fn func(mut env: FunctionEnvMut<RuntimeEnv>) -> Result<(), RuntimeError> {
+        let value = read_shared_rw_memory(&mut env)?;
+        env.data_mut().push_log_item(&value.to_string());
+        Ok(())
+    }
+    Function::new_typed_with_env(store, env, func)
+}
+
+// Circom 2.0
+pub fn print_error_message(store: &mut Store, env: &FunctionEnv<RuntimeEnv>) -> Function {
+    fn func(mut env: FunctionEnvMut<RuntimeEnv>) -> Result<(), RuntimeError> {
+        let message = read_message(&mut env)?;
+
+        let data = env.data_mut();
+        data.error_message.push_str(&message);
+        data.error_message.push('\n');
+        Ok(())
+    }
+    Function::new_typed_with_env(store, env, func)
+}
+
+// Circom 2.0
+pub fn write_buffer_message(store: &mut Store, env: &FunctionEnv<RuntimeEnv>) -> Function {
+    fn func(mut env: FunctionEnvMut<RuntimeEnv>) -> Result<(), RuntimeError> {
+        let message = read_message(&mut env)?;
+
+        let data = env.data_mut();
+        if message == "\n" {
+            data.flush_log_line();
+        } else {
+            data.push_log_item(&message);
+        }
+        Ok(())
+    }
+    Function::new_typed_with_env(store, env, func)
+}
+
+#[cfg(test)]
+mod test {
+    use num::{BigInt, BigUint};
+
+    use crate::{WitnessCalculator, WitnessError, WitnessErrorKind};
+
+    const MOCK_CIRCUIT: &str = include_str!("../tests/data/mock_circuit.wat");
+
+    fn inputs() -> Vec<(String, Vec<BigInt>)> {
+        vec![
+            ("a".to_string(), vec![5.into()]),
+            ("b".to_string(), vec![6.into(), 7.into()]),
+        ]
+    }
+
+    #[test]
+    fn test_logs() {
+        let mut calculator = WitnessCalculator::from_bytes(MOCK_CIRCUIT.as_bytes()).unwrap();
+
+        let witness = calculator.calculate_witness(inputs(), false).unwrap();
+        assert_eq!(witness.prime, BigUint::from(2147483647u32));
+        assert_eq!(witness.values, [1u32, 5, 6, 7].map(BigUint::from));
+        assert_eq!(calculator.logs(), ["x = 42"]);
+
+        // The logs of the previous calculation are dropped.
+        calculator.calculate_witness(inputs(), false).unwrap();
+        assert_eq!(calculator.logs(), ["x = 42"]);
+    }
+
+    #[test]
+    fn test_witness_error() {
+        let mut calculator = WitnessCalculator::from_bytes(MOCK_CIRCUIT.as_bytes()).unwrap();
+
+        let err = calculator
+            .calculate_witness(inputs(), true)
+            .unwrap_err()
+            .downcast::<WitnessError>()
+            .unwrap();
+
+        assert_eq!(err.kind, WitnessErrorKind::AssertFailed);
+        assert_eq!(err.message, "Error in template Mock_0 line: 7");
+        assert_eq!(err.template.as_deref(), Some("Mock_0"));
+        assert_eq!(err.line, Some(7));
+        assert_eq!(err.logs, ["x = 42"]);
+        assert_eq!(
+            err.to_string(),
+            "assert failed in template Mock_0 at line 7"
+        );
+    }
+
+    #[test]
+    fn test_circom_1_is_rejected() {
+        assert!(WitnessCalculator::from_bytes(b"(module)").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use eyre::Result;
+use num::BigUint;
 use wasmer::{Function, Instance, Store, Value};
 
 #[derive(Clone, Debug)]
@@ -18,6 +19,9 @@ pub trait CircomWasm {
     fn get_u32(&self, name: &str, store: &mut Store) -> Result<u32>;
     fn get_field_num_len32(&self, store: &mut Store) -> Result<u32>;
     fn get_raw_prime(&self, store: &mut Store) -> Result<()>;
+    fn get_prime(&self, store: &mut Store) -> Result<BigUint>;
+    fn get_input_size(&self, store: &mut Store) -> Result<u32>;
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32, store: &mut Store) -> Result<i32>;
     fn read_shared_rw_memory(&self, i: u32, store: &mut Store) -> Result<u32>;
     fn write_shared_rw_memory(&self, i: u32, v: u32, store: &mut Store) -> Result<()>;
     fn set_input_signal(&self, hmsb: u32, hlsb: u32, pos: u32, store: &mut Store) -> Result<()>;
@@ -72,6 +76,28 @@ impl CircomWasm for Wasm {
         Ok(())
     }
 
+    fn get_prime(&self, store: &mut Store) -> Result<BigUint> {
+        self.get_raw_prime(store)?;
+
+        let n32 = self.get_field_num_len32(store)?;
+        let mut digits = Vec::with_capacity(n32 as usize);
+        for i in 0..n32 {
+            digits.push(self.read_shared_rw_memory(i, store)?);
+        }
+
+        Ok(BigUint::new(digits))
+    }
+
+    fn get_input_size(&self, store: &mut Store) -> Result<u32> {
+        self.get_u32("getInputSize", store)
+    }
+
+    fn get_input_signal_size(&self, hmsb: u32, hlsb: u32, store: &mut Store) -> Result<i32> {
+        let func = self.func("getInputSignalSize");
+        let result = func.call(store, &[hmsb.into(), hlsb.into()])?;
+        Ok(result[0].unwrap_i32())
+    }
+
     fn read_shared_rw_memory(&self, i: u32, store: &mut Store) -> Result<u32> {
         let func = self.func("readSharedRWMemory");
         let result = func.call(store, &[i.into()])?;
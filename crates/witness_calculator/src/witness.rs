@@ -0,0 +1,329 @@
+use num::BigUint;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Magic bytes of the `wtns` file format.
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+/// The only version of the `wtns` file format that is supported, the one snarkjs writes.
+const WTNS_VERSION: u32 = 2;
+
+/// Section with the field size, the prime and the number of witness values.
+const HEADER_SECTION_ID: u32 = 1;
+
+/// Section with the witness values.
+const VALUES_SECTION_ID: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WtnsError {
+    #[error("failed to read or write wtns file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid wtns magic bytes")]
+    InvalidMagic,
+    #[error("unsupported wtns version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("wtns section {0} is missing")]
+    MissingSection(u32),
+    #[error("wtns section {0} is duplicated")]
+    DuplicatedSection(u32),
+    #[error("wtns section {id} has invalid length {len}")]
+    InvalidSectionLength { id: u32, len: u64 },
+    #[error("witness value #{0} is outside the field")]
+    ValueOutOfField(usize),
+    #[error("witness has {len} values, not enough for {n_public} public signals")]
+    NotEnoughValues { n_public: usize, len: usize },
+}
+
+/// The witness of a Circom circuit.
+///
+/// Values are laid out as Circom does: the constant `1` goes first, then the public outputs,
+/// the public inputs and the private signals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Witness {
+    pub prime: BigUint,
+    pub values: Vec<BigUint>,
+}
+
+impl Witness {
+    /// Returns the range of witness indices that hold the public signals, in the order snarkjs
+    /// puts them into `public.json`. Fails if the witness doesn't have the constant `1` and
+    /// `n_public` signals after it.
+    pub fn public_signal_indices(&self, n_public: usize) -> Result<Range<usize>, WtnsError> {
+        if n_public >= self.values.len() {
+            return Err(WtnsError::NotEnoughValues {
+                n_public,
+                len: self.values.len(),
+            });
+        }
+
+        Ok(1..n_public + 1)
+    }
+
+    /// Returns the public signals of the witness, `n_public` being the number of public outputs
+    /// and inputs of the circuit.
+    pub fn public_signals(&self, n_public: usize) -> Result<&[BigUint], WtnsError> {
+        Ok(&self.values[self.public_signal_indices(n_public)?])
+    }
+
+    /// Field element size in bytes.
+    fn n8(&self) -> usize {
+        // The field element size is always padded to 8 bytes by circom and snarkjs.
+        ((self.prime.bits() as usize + 63) / 64) * 8
+    }
+
+    /// Serializes the witness in the `wtns` v2 format that snarkjs and rapidsnark read.
+    pub fn to_wtns_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        self.write_wtns(&mut result)
+            .expect("writing to Vec<u8> can't fail");
+
+        result
+    }
+
+    /// Parses the witness from the `wtns` v2 format.
+    pub fn from_wtns_bytes(mut src: &[u8]) -> Result<Self, WtnsError> {
+        Self::read_wtns(&mut src)
+    }
+
+    pub fn from_wtns_file(path: impl AsRef<Path>) -> Result<Self, WtnsError> {
+        let mut file = std::fs::File::open(path)?;
+
+        Self::read_wtns(&mut file)
+    }
+
+    pub fn to_wtns_file(&self, path: impl AsRef<Path>) -> Result<(), WtnsError> {
+        let mut file = std::fs::File::create(path)?;
+
+        self.write_wtns(&mut file)
+    }
+
+    pub fn write_wtns<W: Write>(&self, writer: &mut W) -> Result<(), WtnsError> {
+        let n8 = self.n8();
+
+        writer.write_all(WTNS_MAGIC)?;
+        writer.write_all(&WTNS_VERSION.to_le_bytes())?;
+
+        // number of sections
+        writer.write_all(&2u32.to_le_bytes())?;
+
+        writer.write_all(&HEADER_SECTION_ID.to_le_bytes())?;
+        writer.write_all(&((n8 + 8) as u64).to_le_bytes())?;
+        writer.write_all(&(n8 as u32).to_le_bytes())?;
+        writer.write_all(&to_le_bytes_padded(&self.prime, n8))?;
+        writer.write_all(&(self.values.len() as u32).to_le_bytes())?;
+
+        writer.write_all(&VALUES_SECTION_ID.to_le_bytes())?;
+        writer.write_all(&((n8 * self.values.len()) as u64).to_le_bytes())?;
+        for value in &self.values {
+            writer.write_all(&to_le_bytes_padded(value, n8))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_wtns<R: Read>(reader: &mut R) -> Result<Self, WtnsError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != WTNS_MAGIC {
+            return Err(WtnsError::InvalidMagic);
+        }
+
+        let version = read_u32(reader)?;
+        if version != WTNS_VERSION {
+            return Err(WtnsError::UnsupportedVersion(version));
+        }
+
+        let sections_number = read_u32(reader)?;
+
+        let mut header: Option<(usize, BigUint, usize)> = None;
+        let mut values_section: Option<Vec<u8>> = None;
+        for _ in 0..sections_number {
+            let id = read_u32(reader)?;
+            let len = read_u64(reader)?;
+
+            match id {
+                HEADER_SECTION_ID => {
+                    if header.is_some() {
+                        return Err(WtnsError::DuplicatedSection(id));
+                    }
+
+                    let n8 = read_u32(reader)? as usize;
+                    if n8 == 0 || len != n8 as u64 + 8 {
+                        return Err(WtnsError::InvalidSectionLength { id, len });
+                    }
+
+                    let prime = BigUint::from_bytes_le(&read_bytes(reader, n8)?);
+                    let witness_size = read_u32(reader)? as usize;
+
+                    header = Some((n8, prime, witness_size));
+                }
+                VALUES_SECTION_ID => {
+                    if values_section.is_some() {
+                        return Err(WtnsError::DuplicatedSection(id));
+                    }
+
+                    let len = usize::try_from(len)
+                        .map_err(|_| WtnsError::InvalidSectionLength { id, len })?;
+                    values_section = Some(read_bytes(reader, len)?);
+                }
+                // Unknown sections are skipped as snarkjs does.
+                _ => {
+                    std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+                }
+            }
+        }
+
+        let (n8, prime, witness_size) =
+            header.ok_or(WtnsError::MissingSection(HEADER_SECTION_ID))?;
+        let values_section = values_section.ok_or(WtnsError::MissingSection(VALUES_SECTION_ID))?;
+
+        if values_section.len() != n8 * witness_size {
+            return Err(WtnsError::InvalidSectionLength {
+                id: VALUES_SECTION_ID,
+                len: values_section.len() as u64,
+            });
+        }
+
+        let mut values = Vec::with_capacity(witness_size);
+        for (i, chunk) in values_section.chunks_exact(n8).enumerate() {
+            let value = BigUint::from_bytes_le(chunk);
+            if value >= prime {
+                return Err(WtnsError::ValueOutOfField(i));
+            }
+
+            values.push(value);
+        }
+
+        Ok(Self { prime, values })
+    }
+}
+
+fn to_le_bytes_padded(value: &BigUint, len: usize) -> Vec<u8> {
+    let mut bytes = value.to_bytes_le();
+    bytes.resize(len, 0);
+
+    bytes
+}
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, WtnsError> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, WtnsError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, WtnsError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+    use std::str::FromStr;
+
+    use super::{Witness, WtnsError};
+
+    fn bn254_prime() -> BigUint {
+        BigUint::from_str(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_wtns_round_trip() {
+        let witness = Witness {
+            prime: bn254_prime(),
+            values: vec![
+                BigUint::from(1u32),
+                BigUint::from(u64::MAX),
+                bn254_prime() - 1u32,
+                BigUint::from(0u32),
+            ],
+        };
+
+        let bytes = witness.to_wtns_bytes();
+        // magic + version + sections + 2 section headers + header section + 4 values
+        assert_eq!(bytes.len(), 4 + 4 + 4 + 2 * 12 + (4 + 32 + 4) + 4 * 32);
+
+        let parsed = Witness::from_wtns_bytes(&bytes).unwrap();
+        assert_eq!(parsed, witness);
+        assert_eq!(parsed.public_signals(2).unwrap(), &witness.values[1..3]);
+    }
+
+    #[test]
+    fn test_public_signals() {
+        let witness = Witness {
+            prime: bn254_prime(),
+            values: vec![
+                BigUint::from(1u32),
+                BigUint::from(2u32),
+                BigUint::from(3u32),
+            ],
+        };
+
+        assert_eq!(witness.public_signal_indices(2).unwrap(), 1..3);
+        assert_eq!(witness.public_signal_indices(0).unwrap(), 1..1);
+        assert!(witness.public_signals(0).unwrap().is_empty());
+
+        assert!(matches!(
+            witness.public_signals(3),
+            Err(WtnsError::NotEnoughValues {
+                n_public: 3,
+                len: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_public_signals_of_empty_witness() {
+        let witness = Witness {
+            prime: bn254_prime(),
+            values: Vec::new(),
+        };
+
+        assert!(matches!(
+            witness.public_signal_indices(0),
+            Err(WtnsError::NotEnoughValues {
+                n_public: 0,
+                len: 0
+            })
+        ));
+        assert!(matches!(
+            Witness::from_wtns_bytes(&witness.to_wtns_bytes())
+                .unwrap()
+                .public_signals(1),
+            Err(WtnsError::NotEnoughValues {
+                n_public: 1,
+                len: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_wtns_rejects_value_outside_field() {
+        let witness = Witness {
+            prime: bn254_prime(),
+            values: vec![BigUint::from(1u32), bn254_prime()],
+        };
+
+        assert!(matches!(
+            Witness::from_wtns_bytes(&witness.to_wtns_bytes()),
+            Err(WtnsError::ValueOutOfField(1))
+        ));
+    }
+}
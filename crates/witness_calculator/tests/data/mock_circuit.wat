@@ -0,0 +1,92 @@
+;; A mock of a Circom 2.x witness calculator for the unit tests.
+;;
+;; The prime is 2^31 - 1, so a field element takes a single 32-bit word. The input signals are `a`
+;; with 1 value and `b` with 2 values, and the witness is `[1, a, b[0], b[1]]`.
+;;
+;; `init` logs `x = 42` and, if the sanity check is on, fails an assert in template `Mock_0`.
+(module
+  (import "env" "memory" (memory 1))
+  (import "runtime" "exceptionHandler" (func $exception_handler (param i32)))
+  (import "runtime" "printErrorMessage" (func $print_error_message))
+  (import "runtime" "writeBufferMessage" (func $write_buffer_message))
+  (import "runtime" "showSharedRWMemory" (func $show_shared_rw_memory))
+
+  ;; The null-terminated messages that `getMessageChar` returns one after another.
+  (data (i32.const 0) "x =\00\n\00Error in template Mock_0 line: 7\00")
+
+  ;; The witness values are kept at this address.
+  (global $witness i32 (i32.const 64))
+
+  (global $message (mut i32) (i32.const 0))
+  (global $shared_rw_memory (mut i32) (i32.const 0))
+
+  ;; Whether the FNV hash of the input signal name is the one of `a` or `b`.
+  (func $is_a (param $msb i32) (param $lsb i32) (result i32)
+    (i32.and
+      (i32.eq (local.get $msb) (i32.const -1352410036))
+      (i32.eq (local.get $lsb) (i32.const -2046694260))))
+  (func $is_b (param $msb i32) (param $lsb i32) (result i32)
+    (i32.and
+      (i32.eq (local.get $msb) (i32.const -1352409268))
+      (i32.eq (local.get $lsb) (i32.const -2046692955))))
+
+  (func (export "init") (param $sanity_check i32)
+    (global.set $message (i32.const 0))
+    (i32.store (global.get $witness) (i32.const 1))
+
+    ;; log("x =", 42)
+    (call $write_buffer_message)
+    (global.set $shared_rw_memory (i32.const 42))
+    (call $show_shared_rw_memory)
+    (call $write_buffer_message)
+
+    (if (local.get $sanity_check)
+      (then
+        (call $print_error_message)
+        (call $exception_handler (i32.const 4)))))
+
+  (func (export "getFieldNumLen32") (result i32)
+    (i32.const 1))
+
+  (func (export "getRawPrime")
+    (global.set $shared_rw_memory (i32.const 2147483647)))
+
+  (func (export "readSharedRWMemory") (param $i i32) (result i32)
+    (global.get $shared_rw_memory))
+
+  (func (export "writeSharedRWMemory") (param $i i32) (param $value i32)
+    (global.set $shared_rw_memory (local.get $value)))
+
+  (func (export "getMessageChar") (result i32)
+    (local $char i32)
+    (local.set $char (i32.load8_u (global.get $message)))
+    (global.set $message (i32.add (global.get $message) (i32.const 1)))
+    (local.get $char))
+
+  (func (export "getInputSize") (result i32)
+    (i32.const 3))
+
+  (func (export "getInputSignalSize") (param $msb i32) (param $lsb i32) (result i32)
+    (if (call $is_a (local.get $msb) (local.get $lsb))
+      (then (return (i32.const 1))))
+    (if (call $is_b (local.get $msb) (local.get $lsb))
+      (then (return (i32.const 2))))
+    (i32.const -1))
+
+  (func (export "setInputSignal") (param $msb i32) (param $lsb i32) (param $pos i32)
+    (local $index i32)
+    (local.set $index
+      (select
+        (i32.const 1)
+        (i32.add (i32.const 2) (local.get $pos))
+        (call $is_a (local.get $msb) (local.get $lsb))))
+    (i32.store
+      (i32.add (global.get $witness) (i32.mul (local.get $index) (i32.const 4)))
+      (global.get $shared_rw_memory)))
+
+  (func (export "getWitnessSize") (result i32)
+    (i32.const 4))
+
+  (func (export "getWitness") (param $i i32)
+    (global.set $shared_rw_memory
+      (i32.load (i32.add (global.get $witness) (i32.mul (local.get $i) (i32.const 4)))))))
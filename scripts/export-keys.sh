@@ -33,4 +33,6 @@ snarkjs zkey export solidityverifier ${BUILD_DIR}/circuit_final.zkey ${BUILD_DIR
 # Removing redudant files
 rm -rf ${BUILD_DIR}/zkey ${BUILD_DIR}/${CIRCUIT_NAME}_0000.zkey
 
-echo -e "\nKeys exported $BUILD_DIR/circuit_final.zkey, $BUILD_DIR/verification_key.json, $BUILD_DIR/verifier.sol"
\ No newline at end of file
+$(dirname "$0")/export-manifest.sh ${CIRCUIT_NAME}
+
+echo -e "\nKeys exported $BUILD_DIR/circuit_final.zkey, $BUILD_DIR/verification_key.json, $BUILD_DIR/verifier.sol, $BUILD_DIR/manifest.json"
\ No newline at end of file
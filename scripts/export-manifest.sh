@@ -0,0 +1,44 @@
+#!/bin/bash
+set -e
+
+CIRCUIT_NAME=$1
+BUILD_DIR=""
+
+# Define the build directory where intermediate files will be stored
+if [ -d ./circuits ]; then
+    BUILD_DIR="./$CIRCUIT_NAME.dev"
+elif [ -d ../circuits ]; then
+    BUILD_DIR="../$CIRCUIT_NAME.dev"
+else
+    echo "Error: can't find way to circuits folder: unknow directory."
+    exit 1
+fi
+
+sha256() {
+    if command -v sha256sum > /dev/null; then
+        sha256sum "$1" | cut -d ' ' -f 1
+    else
+        shasum -a 256 "$1" | cut -d ' ' -f 1
+    fi
+}
+
+VERIFICATION_KEY=${BUILD_DIR}/verification_key.json
+
+# Recording the artifacts' hashes and the circuit metadata in manifest.json
+echo -e "\nExporting artifact manifest..."
+
+cat > ${BUILD_DIR}/manifest.json <<MANIFEST
+{
+  "circuit": "${CIRCUIT_NAME}",
+  "protocol": "$(node -p "require('${VERIFICATION_KEY}').protocol")",
+  "curve": "$(node -p "require('${VERIFICATION_KEY}').curve")",
+  "nPublic": $(node -p "require('${VERIFICATION_KEY}').nPublic"),
+  "sha256": {
+    "witnessCalculator": "$(sha256 ${BUILD_DIR}/${CIRCUIT_NAME}.wasm)",
+    "provingKey": "$(sha256 ${BUILD_DIR}/circuit_final.zkey)",
+    "verificationKey": "$(sha256 ${VERIFICATION_KEY})"
+  }
+}
+MANIFEST
+
+echo -e "\nArtifact manifest exported ${BUILD_DIR}/manifest.json"
@@ -0,0 +1,207 @@
+//! Integrity checks of the Circom artifacts: the witness calculator, the proving key and the
+//! verification key.
+//!
+//! `scripts/export-manifest.sh` records the SHA-256 of every artifact and the circuit metadata
+//! in `manifest.json` right after the trusted setup, so a zkey from one setup can't be silently
+//! used with a verification key from another one. The artifacts can also be embedded into the
+//! binary with the `embed-artifacts` and `embed-proving-key` features.
+
+use std::fs::File;
+use std::path::Path;
+
+use bdk::bitcoin::hashes::{sha256, Hash};
+use eyre::{eyre, Context, Result};
+use rapidsnark::{ToBytes, VerificationKey, CURVE, PROTOCOL};
+
+use crate::config::CircomConfig;
+use crate::pubsignals::PUBSIGNALS_NUMBER;
+
+/// The manifest of the Circom artifacts produced by `scripts/export-manifest.sh`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactManifest {
+    pub circuit: String,
+    pub protocol: String,
+    pub curve: String,
+    pub n_public: usize,
+    pub sha256: ArtifactHashes,
+}
+
+/// Hex-encoded SHA-256 of the artifact files.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactHashes {
+    pub witness_calculator: String,
+    pub proving_key: String,
+    pub verification_key: String,
+}
+
+impl ArtifactManifest {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path).wrap_err("failed to open artifact manifest")?;
+
+        serde_json::from_reader(file).wrap_err("failed to parse artifact manifest")
+    }
+
+    /// Checks the circuit metadata and the hashes of the artifacts from the config.
+    pub fn check(&self, config: &CircomConfig) -> Result<()> {
+        if self.protocol != PROTOCOL {
+            return Err(eyre!("unsupported protocol in manifest: {}", self.protocol));
+        }
+        if self.curve != CURVE {
+            return Err(eyre!("unsupported curve in manifest: {}", self.curve));
+        }
+        if self.n_public != PUBSIGNALS_NUMBER {
+            return Err(eyre!(
+                "manifest's circuit has {} public signals, expected {PUBSIGNALS_NUMBER}",
+                self.n_public
+            ));
+        }
+
+        let artifacts = [(
+            "verification key",
+            config.verification_key()?,
+            &self.sha256.verification_key,
+        )];
+
+        // The responder-only build uses the verification key alone.
+        #[cfg(feature = "prover")]
+        let artifacts = artifacts.into_iter().chain([
+            (
+                "witness calculator",
+                config.witness_calculator_wasm()?,
+                &self.sha256.witness_calculator,
+            ),
+            (
+                "proving key",
+                config.proving_key()?,
+                &self.sha256.proving_key,
+            ),
+        ]);
+
+        for (name, artifact, expected_hash) in artifacts {
+            let hash = sha256_hex(&artifact);
+
+            if !hash.eq_ignore_ascii_case(expected_hash) {
+                return Err(eyre!(
+                    "{name} doesn't match the {} circuit manifest: expected SHA-256 \
+                     {expected_hash}, got {hash}",
+                    self.circuit,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the artifacts against the manifest if it's configured, and the verification key against
+/// the atomic-swap circuit's public signals either way.
+pub fn check_artifacts(config: &CircomConfig, verification_key: &VerificationKey) -> Result<()> {
+    let n_public = verification_key.gamma_abc_g1.len().saturating_sub(1);
+    if n_public != PUBSIGNALS_NUMBER {
+        return Err(eyre!(
+            "verification key has {n_public} public signals, expected {PUBSIGNALS_NUMBER}"
+        ));
+    }
+
+    let Some(manifest_path) = &config.manifest_path else {
+        return Ok(());
+    };
+
+    ArtifactManifest::from_file(manifest_path)?.check(config)
+}
+
+/// Returns the SHA-256 of the verification key's canonical binary encoding, so it doesn't depend
+/// on the formatting of `verification_key.json`. Both swap parties compare it to make sure they
+/// use the same circuit and trusted setup.
+pub fn verification_key_hash(verification_key: &VerificationKey) -> Result<[u8; 32]> {
+    let bytes = verification_key
+        .to_bytes()
+        .wrap_err("failed to serialize verification key")?;
+
+    Ok(sha256::Hash::hash(&bytes).into_inner())
+}
+
+/// Returns the hex-encoded SHA-256 as `sha256sum` prints it.
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(sha256::Hash::hash(bytes).into_inner())
+}
+
+/// Circom artifacts embedded into the binary, see `build.rs`.
+pub mod embedded {
+    #[cfg(all(feature = "prover", feature = "embed-artifacts"))]
+    pub const WITNESS_CALCULATOR: Option<&[u8]> =
+        Some(include_bytes!(env!("ATOMIC_SWAP_WASM_PATH")));
+    #[cfg(all(feature = "prover", not(feature = "embed-artifacts")))]
+    pub const WITNESS_CALCULATOR: Option<&[u8]> = None;
+
+    #[cfg(feature = "embed-artifacts")]
+    pub const VERIFICATION_KEY: Option<&[u8]> =
+        Some(include_bytes!(env!("ATOMIC_SWAP_VERIFICATION_KEY_PATH")));
+    #[cfg(not(feature = "embed-artifacts"))]
+    pub const VERIFICATION_KEY: Option<&[u8]> = None;
+
+    #[cfg(feature = "embed-proving-key")]
+    pub const PROVING_KEY: Option<&[u8]> =
+        Some(include_bytes!(env!("ATOMIC_SWAP_PROVING_KEY_PATH")));
+    #[cfg(all(feature = "prover", not(feature = "embed-proving-key")))]
+    pub const PROVING_KEY: Option<&[u8]> = None;
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{sha256_hex, ArtifactHashes, ArtifactManifest};
+    use crate::config::CircomConfig;
+
+    fn write_artifact(name: &str, content: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_manifest_check() {
+        let config = CircomConfig {
+            witnes_calculator_path: Some(write_artifact("atomic_swap.wasm", b"wasm")),
+            proving_key_path: Some(write_artifact("circuit_final.zkey", b"zkey")),
+            verification_key_path: Some(write_artifact("verification_key.json", b"{}")),
+            manifest_path: None,
+        };
+
+        // echo -n abc | sha256sum
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let mut manifest = ArtifactManifest {
+            circuit: "atomic_swap".to_string(),
+            protocol: "groth16".to_string(),
+            curve: "bn128".to_string(),
+            n_public: 9,
+            sha256: ArtifactHashes {
+                witness_calculator: sha256_hex(b"wasm"),
+                proving_key: sha256_hex(b"zkey"),
+                verification_key: sha256_hex(b"{}"),
+            },
+        };
+        manifest.check(&config).unwrap();
+
+        manifest.sha256.verification_key = sha256_hex(b"zkey");
+        assert!(manifest.check(&config).is_err());
+
+        manifest.sha256.verification_key = sha256_hex(b"{}");
+        #[cfg(feature = "prover")]
+        {
+            manifest.sha256.proving_key = sha256_hex(b"{}");
+            assert!(manifest.check(&config).is_err());
+
+            manifest.sha256.proving_key = sha256_hex(b"zkey");
+        }
+        manifest.n_public = 8;
+        assert!(manifest.check(&config).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Bitcoin operations off the async runtime.
+//!
+//! BDK's wallet sync and the RPC calls block, so they run on Tokio's blocking thread pool instead
+//! of the runtime's worker threads, which keeps the Ethereum listeners and the Bitcoin pollers
+//! running concurrently in one process.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use bdk::bitcoin::Network as BitcoinNetwork;
+use bdk::blockchain::{Blockchain, RpcBlockchain as BitcoinClient};
+use bdk::database::MemoryDatabase;
+use bdk::{FeeRate, Wallet};
+use eyre::{eyre, Context, Result};
+use tokio_util::sync::CancellationToken;
+
+/// The participant's funding wallet and its Bitcoin RPC client that are shared with the blocking
+/// tasks. BDK keeps the wallet's database in a `RefCell`, so the wallet isn't `Sync` and is behind
+/// a mutex, which also keeps the concurrent syncs and transaction builds from interleaving.
+#[derive(Clone)]
+pub struct BitcoinBackend {
+    wallet: Arc<Mutex<Wallet<MemoryDatabase>>>,
+    client: Arc<BitcoinClient>,
+    network: BitcoinNetwork,
+}
+
+impl BitcoinBackend {
+    pub fn new(wallet: Wallet<MemoryDatabase>, client: BitcoinClient) -> Self {
+        Self {
+            network: wallet.network(),
+            wallet: Arc::new(Mutex::new(wallet)),
+            client: Arc::new(client),
+        }
+    }
+
+    pub fn network(&self) -> BitcoinNetwork {
+        self.network
+    }
+
+    /// Runs `f` with the funding wallet and the client on the blocking thread pool.
+    pub async fn with_wallet<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Wallet<MemoryDatabase>, &BitcoinClient) -> Result<T> + Send + 'static,
+    {
+        let wallet = self.wallet.clone();
+        let client = self.client.clone();
+
+        spawn_blocking(move || {
+            let wallet = wallet
+                .lock()
+                .map_err(|_| eyre!("Bitcoin wallet lock is poisoned"))?;
+
+            f(&wallet, &client)
+        })
+        .await
+    }
+
+    /// Runs `f` with the client on the blocking thread pool, e.g. to sync a swap's wallet.
+    pub async fn with_client<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&BitcoinClient) -> Result<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+
+        spawn_blocking(move || f(&client)).await
+    }
+}
+
+/// Runs `f` on the blocking thread pool.
+pub(crate) async fn spawn_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .wrap_err("Bitcoin task has panicked")?
+}
+
+/// Estimates the feerate for the confirmation within 2 blocks, or returns the `fallback` one if the
+/// node fails to estimate it.
+pub fn estimate_feerate(client: &BitcoinClient, fallback: Option<FeeRate>) -> Result<FeeRate> {
+    match client.estimate_fee(2) {
+        Ok(feerate) => Ok(feerate),
+        Err(err) => {
+            let feerate = fallback.ok_or_else(|| {
+                eyre!("failed to estimate Bitcoin feerate and no fallback one is configured: {err}")
+            })?;
+            println!(
+                "| Failed to estimate Bitcoin feerate, using the fallback {} sat/vB: {err}",
+                feerate.as_sat_per_vb()
+            );
+
+            Ok(feerate)
+        }
+    }
+}
+
+/// Runs the `future` until it completes or the `cancellation` token is cancelled. A blocking
+/// task the future waits for is detached then and runs to its end.
+pub async fn cancellable<T>(
+    cancellation: &CancellationToken,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        result = future => result,
+        _ = cancellation.cancelled() => Err(eyre!("cancelled")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::cancellable;
+
+    #[tokio::test]
+    async fn test_cancellable() {
+        let cancellation = CancellationToken::new();
+        assert_eq!(
+            cancellable(&cancellation, async { Ok(42) }).await.unwrap(),
+            42
+        );
+
+        let pending = tokio::time::sleep(Duration::from_secs(3600));
+        cancellation.cancel();
+        assert!(cancellable(&cancellation, async {
+            pending.await;
+            Ok(())
+        })
+        .await
+        .is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use bdk::bitcoin::secp256k1::{All, Secp256k1, SecretKey as SecpSecretKey};
-use bdk::bitcoin::{Network as BitcoinNetwork, PrivateKey as BitcoinPrivateKey};
+use bdk::bitcoin::{
+    Address as BitcoinAddress, Network as BitcoinNetwork, PrivateKey as BitcoinPrivateKey,
+    PublicKey as BitcoinPublicKey,
+};
 use bdk::blockchain::rpc::RpcSyncParams;
 use bdk::blockchain::{
     rpc::Auth as BdkRpcAuth, ConfigurableBlockchain, RpcBlockchain as BitcoinClient,
@@ -7,21 +10,51 @@ use bdk::blockchain::{
 };
 use bdk::database::MemoryDatabase;
 use bdk::wallet::wallet_name_from_descriptor;
-use bdk::{descriptor, SyncOptions};
+use bdk::SyncOptions;
 use ethers::prelude::Ws;
 use ethers::providers::Provider as EthereumClient;
 use ethers::types::Address as EthereumAddress;
-use eyre::{Context, Result};
+use eyre::{eyre, Context, Result};
+use rapidsnark::Verifier;
+use std::borrow::Cow;
 use std::path::PathBuf;
+#[cfg(feature = "prover")]
+use witness_calculator::WitnessCalculator;
+use zeroize::Zeroizing;
+
+use crate::artifacts::embedded;
+use crate::gas::GasConfig;
+use crate::hd::{HdKeys, SwapKeys};
+use crate::keystore;
+use crate::relayer::{RelayerClientConfig, RelayerConfig};
+use crate::signer::{BitcoinSigner, EthereumSignerConfig};
+use crate::store::SwapStore;
 
 #[derive(serde::Deserialize)]
 pub struct Config {
     pub atomic_swap_contract_address: EthereumAddress,
+    /// Whether the deposit to Ethereum is made with the atomic-swap proof, so the
+    /// `VerifiedDepositor` contract checks it on-chain.
+    #[serde(default)]
+    pub deposit_with_proof: bool,
     pub ethereum_ws_rpc_url: String,
+    /// Number of confirmations of the Ethereum events before they are handled.
+    #[serde(default = "default_ethereum_confirmations")]
+    pub ethereum_confirmations: u64,
+    /// Whether the revealed swap secret is taken from the pending withdraw to redeem on Bitcoin
+    /// before the withdraw is mined.
+    #[serde(default)]
+    pub watch_mempool: bool,
     pub bitcoin_rpc: BitcoinRpcConfig,
     pub circom: CircomConfig,
 
     pub swap_params: SwapParams,
+    /// Gas strategy of the `Depositor` calls.
+    #[serde(default)]
+    pub gas: GasConfig,
+    /// The relayer service run with the `relayer` command, see [`crate::relayer`].
+    #[serde(default)]
+    pub relayer: Option<RelayerConfig>,
 
     #[serde(rename = "alice")]
     pub alice_config: WalletsConfig,
@@ -29,27 +62,298 @@ pub struct Config {
     pub bob_config: WalletsConfig,
 }
 
+fn default_ethereum_confirmations() -> u64 {
+    3
+}
+
 impl Config {
+    /// Returns the [`ethers::providers::Provider`] that can be used to send transactions to
+    /// the Ethereum network.
+    pub async fn ethereum_client(&self) -> Result<EthereumClient<Ws>> {
+        let provider = EthereumClient::<Ws>::connect(self.ethereum_ws_rpc_url.clone()).await?;
+
+        Ok(provider)
+    }
+
+    /// Checks the participants' configs for the combinations that would fail during the swap.
+    pub fn validate(&self) -> Result<()> {
+        self.alice_config
+            .validate()
+            .wrap_err("invalid alice config")?;
+        self.bob_config.validate().wrap_err("invalid bob config")
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SwapParams {
+    pub sats_to_swap: u64,
+    pub gwei_to_swap: u64,
+    pub bitcoin_csv_delay: u32,
+    pub ethereum_timelock_secs: u64,
+}
+
+/// Participant's private keys. They are either derived from a BIP39 mnemonic, see [`crate::hd`],
+/// or set as single keys that are reused by every swap. Each secret is either set in plaintext,
+/// which is meant only for development, or read from an encrypted keystore, see
+/// [`crate::keystore`].
+#[derive(serde::Deserialize)]
+pub struct WalletsConfig {
+    #[serde(default)]
+    pub mnemonic: Option<String>,
+    /// The mnemonic encrypted with `encrypt-mnemonic`.
+    #[serde(default)]
+    pub mnemonic_keystore: Option<PathBuf>,
+    /// The swap store that tracks the derivation indexes of the per-swap keys. It's required with
+    /// the mnemonic.
+    #[serde(default)]
+    pub swap_store_path: Option<PathBuf>,
+    #[serde(default)]
+    pub bitcoin_private_key: Option<SecpSecretKey>,
+    /// The Bitcoin key encrypted with `encrypt-key`.
+    #[serde(default)]
+    pub bitcoin_keystore: Option<PathBuf>,
+    #[serde(default)]
+    pub ethereum_private_key: Option<SecpSecretKey>,
+    /// The Ethereum JSON keystore.
+    #[serde(default)]
+    pub ethereum_keystore: Option<PathBuf>,
+    /// The environment variable with the keystores' passphrase. It's prompted for if the variable
+    /// is not set.
+    #[serde(default)]
+    pub keystore_passphrase_env: Option<String>,
+    /// Descriptors of the funding wallet that override the ones derived from the keys, e.g. the
+    /// public descriptors of a hardware wallet for [`BitcoinSigner::Psbt`].
+    #[serde(default)]
+    pub bitcoin_wallet_descriptor: Option<String>,
+    #[serde(default)]
+    pub bitcoin_wallet_change_descriptor: Option<String>,
+    /// The signer of the funding transaction.
+    #[serde(default)]
+    pub bitcoin_signer: BitcoinSigner,
+    /// The signer of the Ethereum transactions. The Ethereum key is optional with the remote
+    /// signer, then the deposits are made for the signer's address.
+    #[serde(default)]
+    pub ethereum_signer: EthereumSignerConfig,
+    /// The relayer the deposit is withdrawn through, so the recipient doesn't pay for gas. It
+    /// requires the `RelayedDepositor` contract.
+    #[serde(default)]
+    pub withdraw_relayer: Option<RelayerClientConfig>,
+}
+
+/// Decrypted participant's private keys.
+pub enum WalletKeys {
+    /// The keys derived from the mnemonic.
+    Hd(HdKeys),
+    /// The single keys reused by every swap.
+    Single {
+        bitcoin: SecpSecretKey,
+        ethereum: Option<SecpSecretKey>,
+    },
+}
+
+impl WalletsConfig {
+    /// Checks that the relayed withdraw can be signed. It's signed as EIP-712 typed data by the
+    /// Ethereum swap key, or by the signer without one, and the remote signer can't sign the typed
+    /// data, see [`crate::web3signer`].
+    pub fn validate(&self) -> Result<()> {
+        let has_ethereum_swap_key = [
+            self.mnemonic.is_some(),
+            self.mnemonic_keystore.is_some(),
+            self.ethereum_private_key.is_some(),
+            self.ethereum_keystore.is_some(),
+        ]
+        .contains(&true);
+        let is_remote_signer = matches!(
+            self.ethereum_signer,
+            EthereumSignerConfig::Web3Signer { .. }
+        );
+
+        if self.withdraw_relayer.is_some() && is_remote_signer && !has_ethereum_swap_key {
+            return Err(eyre!(
+                "withdraw_relayer requires mnemonic, ethereum_private_key or ethereum_keystore \
+                 with the web3signer Ethereum signer, as the relayed withdraw is signed as typed \
+                 data"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the plaintext keys or decrypts the keystores. The passphrase is read once for all
+    /// keystores.
+    pub fn keys(&self, name: &str, network: BitcoinNetwork) -> Result<WalletKeys> {
+        let mut cached_passphrase = None;
+        let mut passphrase = || -> Result<Zeroizing<String>> {
+            if cached_passphrase.is_none() {
+                cached_passphrase = Some(keystore::read_passphrase(
+                    self.keystore_passphrase_env.as_deref(),
+                    &format!("{name}'s keystore passphrase: "),
+                )?);
+            }
+
+            Ok(cached_passphrase.clone().expect("passphrase is read above"))
+        };
+
+        let has_single_keys = [
+            self.bitcoin_private_key.is_some(),
+            self.bitcoin_keystore.is_some(),
+            self.ethereum_private_key.is_some(),
+            self.ethereum_keystore.is_some(),
+        ]
+        .contains(&true);
+
+        let mnemonic = match (&self.mnemonic, &self.mnemonic_keystore) {
+            (Some(mnemonic), None) => Some(Zeroizing::new(mnemonic.clone())),
+            (None, Some(path)) => Some(keystore::decrypt_mnemonic(path, &passphrase()?)?),
+            (None, None) => None,
+            (Some(_), Some(_)) => {
+                return Err(eyre!("either mnemonic or mnemonic_keystore must be set"))
+            }
+        };
+
+        if let Some(mnemonic) = mnemonic {
+            if has_single_keys {
+                return Err(eyre!(
+                    "private keys and keystores can't be set along with the mnemonic"
+                ));
+            }
+
+            return Ok(WalletKeys::Hd(HdKeys::from_mnemonic(&mnemonic, network)?));
+        }
+
+        let bitcoin = match (&self.bitcoin_private_key, &self.bitcoin_keystore) {
+            (Some(key), None) => *key,
+            (None, Some(path)) => keystore::decrypt_bitcoin_key(path, &passphrase()?)?,
+            _ => {
+                return Err(eyre!(
+                    "either mnemonic, bitcoin_private_key or bitcoin_keystore must be set"
+                ))
+            }
+        };
+
+        let ethereum = match (&self.ethereum_private_key, &self.ethereum_keystore) {
+            (Some(key), None) => Some(*key),
+            (None, Some(path)) => Some(keystore::decrypt_ethereum_key(path, &passphrase()?)?),
+            (None, None) => None,
+            (Some(_), Some(_)) => {
+                return Err(eyre!(
+                    "either ethereum_private_key or ethereum_keystore must be set"
+                ))
+            }
+        };
+
+        Ok(WalletKeys::Single { bitcoin, ethereum })
+    }
+
+    /// Returns the descriptor and the optional change descriptor of the funding wallet, either
+    /// the configured ones or the ones derived from the `keys`.
+    pub fn bitcoin_wallet_descriptors(
+        &self,
+        keys: &WalletKeys,
+        network: BitcoinNetwork,
+    ) -> (String, Option<String>) {
+        match &self.bitcoin_wallet_descriptor {
+            Some(descriptor) => (
+                descriptor.clone(),
+                self.bitcoin_wallet_change_descriptor.clone(),
+            ),
+            None => keys.bitcoin_wallet_descriptors(network),
+        }
+    }
+
+    /// Opens the swap store if it's configured. It's required for the keys derived from the
+    /// mnemonic.
+    pub fn swap_store(&self, keys: &WalletKeys) -> Result<Option<SwapStore>> {
+        match (&self.swap_store_path, keys) {
+            (Some(path), _) => SwapStore::open(path).map(Some),
+            (None, WalletKeys::Hd(_)) => Err(eyre!(
+                "swap_store_path must be set to track the derivation indexes of the mnemonic"
+            )),
+            (None, WalletKeys::Single { .. }) => Ok(None),
+        }
+    }
+}
+
+impl WalletKeys {
+    /// Returns the descriptor and the optional change descriptor of the funding wallet: the BIP86
+    /// one for the mnemonic, or P2WPKH for the single key.
+    pub fn bitcoin_wallet_descriptors(&self, network: BitcoinNetwork) -> (String, Option<String>) {
+        match self {
+            Self::Hd(keys) => {
+                let (descriptor, change_descriptor) = keys.bitcoin_wallet_descriptors();
+                (descriptor, Some(change_descriptor))
+            }
+            Self::Single { bitcoin, .. } => {
+                let private_key = BitcoinPrivateKey::new(*bitcoin, network);
+                (format!("wpkh({})", private_key.to_wif()), None)
+            }
+        }
+    }
+
+    /// Returns the key of the Ethereum account that pays for the transactions if it's set.
+    pub fn ethereum_funding_key(&self, secp_ctx: &Secp256k1<All>) -> Result<Option<SecpSecretKey>> {
+        match self {
+            Self::Hd(keys) => keys.ethereum_funding_key(secp_ctx).map(Some),
+            Self::Single { ethereum, .. } => Ok(*ethereum),
+        }
+    }
+
+    /// Returns the keys of the swap with the derivation `index`. The single keys are the same
+    /// for every swap, and the redeem address is the P2WPKH one of the Bitcoin key.
+    pub fn swap_keys(
+        &self,
+        secp_ctx: &Secp256k1<All>,
+        network: BitcoinNetwork,
+        index: Option<u32>,
+    ) -> Result<SwapKeys> {
+        match (self, index) {
+            (Self::Hd(keys), Some(index)) => keys.swap_keys(secp_ctx, index),
+            (Self::Hd(_), None) => Err(eyre!("derivation index of the swap keys is not set")),
+            (Self::Single { bitcoin, ethereum }, _) => Ok(SwapKeys {
+                bitcoin: *bitcoin,
+                bitcoin_redeem_address: BitcoinAddress::p2wpkh(
+                    &BitcoinPublicKey::new(bitcoin.public_key(secp_ctx)),
+                    network,
+                )?,
+                ethereum: *ethereum,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct BitcoinRpcConfig {
+    pub url: String,
+    pub auth: BdkRpcAuth,
+    pub network: BitcoinNetwork,
+    pub start_block_timestamp: u64,
+    /// Feerate in sat/vB used if the node fails to estimate one, e.g. on regtest without enough
+    /// transactions. The transactions fail to be built then if it's omitted.
+    #[serde(default)]
+    pub fallback_feerate_sat_per_vb: Option<f32>,
+}
+
+impl BitcoinRpcConfig {
     /// Return the [`bdk::Wallet<MemoryDatabase>`] that can be used to operate with UTXOs and the
     /// [`BitcoinClient`] for retrieving the available UTXOs from the Bitcoin network.
     pub fn bitcoin_wallet(
         &self,
         secp_ctx: &Secp256k1<All>,
-        secret_key: SecpSecretKey,
+        descriptor: &str,
+        change_descriptor: Option<&str>,
     ) -> Result<(bdk::Wallet<MemoryDatabase>, BitcoinClient)> {
-        let network = self.bitcoin_rpc.network;
-        let private_key = BitcoinPrivateKey::new(secret_key, network);
+        let network = self.network;
 
         let wallet = bdk::Wallet::new(
-            descriptor!(wpkh(private_key))?,
-            None,
+            descriptor,
+            change_descriptor,
             network,
             MemoryDatabase::default(),
         )
         .wrap_err("failed to initialize BDK wallet")?;
 
         let bitcoin_client = self
-            .bitcoin_client(secp_ctx, secret_key)
+            .bitcoin_client(secp_ctx, descriptor, change_descriptor)
             .wrap_err("failed to initialize Bitcoin RPC client for wallet")?;
 
         wallet
@@ -59,34 +363,26 @@ impl Config {
         Ok((wallet, bitcoin_client))
     }
 
-    /// Returns the [`ethers::providers::Provider`] that can be used to send transactions to
-    /// the Ethereum network.
-    pub async fn ethereum_client(&self) -> Result<EthereumClient<Ws>> {
-        let provider = EthereumClient::<Ws>::connect(self.ethereum_ws_rpc_url.clone()).await?;
-
-        Ok(provider)
-    }
-
     /// Returns the [`bdk::blockchain::RpcBlockchain`] for the wallet. It will be used there to
     /// retrieve the UTXOs from Bitcoin.
     fn bitcoin_client(
         &self,
         secp_ctx: &Secp256k1<All>,
-        secret_key: SecpSecretKey,
+        descriptor: &str,
+        change_descriptor: Option<&str>,
     ) -> Result<BitcoinClient> {
-        let network = self.bitcoin_rpc.network;
-        let private_key = BitcoinPrivateKey::new(secret_key, network);
+        let network = self.network;
         let wallet_name =
-            wallet_name_from_descriptor(descriptor!(wpkh(private_key))?, None, network, secp_ctx)?;
+            wallet_name_from_descriptor(descriptor, change_descriptor, network, secp_ctx)?;
 
         let rpc_sync_params = RpcSyncParams {
-            start_time: self.bitcoin_rpc.start_block_timestamp,
+            start_time: self.start_block_timestamp,
             ..Default::default()
         };
 
         let config = BdkRpcConfig {
-            url: self.bitcoin_rpc.url.clone(),
-            auth: self.bitcoin_rpc.auth.clone(),
+            url: self.url.clone(),
+            auth: self.auth.clone(),
             network,
             wallet_name,
             sync_params: Some(rpc_sync_params),
@@ -98,31 +394,80 @@ impl Config {
     }
 }
 
+/// Paths to the Circom artifacts. A path can be omitted if the artifact is embedded into the
+/// binary with the `embed-artifacts` or `embed-proving-key` feature, and overrides the embedded
+/// one otherwise.
 #[derive(Clone, serde::Deserialize)]
-pub struct SwapParams {
-    pub sats_to_swap: u64,
-    pub gwei_to_swap: u64,
-    pub bitcoin_csv_delay: u32,
-    pub ethereum_timelock_secs: u64,
+pub struct CircomConfig {
+    #[serde(default)]
+    pub witnes_calculator_path: Option<PathBuf>,
+    #[serde(default)]
+    pub proving_key_path: Option<PathBuf>,
+    #[serde(default)]
+    pub verification_key_path: Option<PathBuf>,
+    /// The artifact manifest from `scripts/export-manifest.sh`. If it's set, the artifacts are
+    /// checked against it at startup.
+    #[serde(default)]
+    pub manifest_path: Option<PathBuf>,
 }
 
-#[derive(serde::Deserialize)]
-pub struct WalletsConfig {
-    pub bitcoin_private_key: SecpSecretKey,
-    pub ethereum_private_key: SecpSecretKey,
-}
+impl CircomConfig {
+    /// Returns the [`Verifier`] with the prepared verification key, so it is read and processed
+    /// once instead of for every proof.
+    pub fn verifier(&self) -> Result<Verifier> {
+        let verification_key = self.verification_key()?;
 
-#[derive(serde::Deserialize)]
-pub struct BitcoinRpcConfig {
-    pub url: String,
-    pub auth: BdkRpcAuth,
-    pub network: BitcoinNetwork,
-    pub start_block_timestamp: u64,
+        let verifier =
+            Verifier::from_json(&verification_key).wrap_err("failed to parse verification key")?;
+
+        Ok(verifier)
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn witness_calculator(&self) -> Result<WitnessCalculator> {
+        let wasm = self.witness_calculator_wasm()?;
+
+        WitnessCalculator::from_bytes(&wasm).wrap_err("failed to compile witness calculator")
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn witness_calculator_wasm(&self) -> Result<Cow<'static, [u8]>> {
+        load_artifact(
+            "witness calculator",
+            &self.witnes_calculator_path,
+            embedded::WITNESS_CALCULATOR,
+        )
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn proving_key(&self) -> Result<Cow<'static, [u8]>> {
+        load_artifact("proving key", &self.proving_key_path, embedded::PROVING_KEY)
+    }
+
+    pub fn verification_key(&self) -> Result<Cow<'static, [u8]>> {
+        load_artifact(
+            "verification key",
+            &self.verification_key_path,
+            embedded::VERIFICATION_KEY,
+        )
+    }
 }
 
-#[derive(Clone, serde::Deserialize)]
-pub struct CircomConfig {
-    pub witnes_calculator_path: PathBuf,
-    pub proving_key_path: PathBuf,
-    pub verification_key_path: PathBuf,
+/// Reads the artifact from the configured path, or returns the embedded one if the path is not
+/// set.
+fn load_artifact(
+    name: &str,
+    path: &Option<PathBuf>,
+    embedded: Option<&'static [u8]>,
+) -> Result<Cow<'static, [u8]>> {
+    match (path, embedded) {
+        (Some(path), _) => {
+            let artifact = std::fs::read(path)
+                .wrap_err_with(|| format!("failed to read {name} from {}", path.display()))?;
+
+            Ok(Cow::Owned(artifact))
+        }
+        (None, Some(embedded)) => Ok(Cow::Borrowed(embedded)),
+        (None, None) => Err(eyre!("{name} path is not set and it is not embedded")),
+    }
 }
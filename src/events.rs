@@ -0,0 +1,436 @@
+//! Follower of the contract events that survives reorgs and WebSocket disconnects.
+//!
+//! The subscription only delivers the logs early: a block's logs are returned once the block has
+//! the configured number of confirmations, and then they are fetched with `eth_getLogs` for all
+//! the newly confirmed blocks, so the logs that the subscription has missed, e.g. while
+//! disconnected or because it lags behind the node, aren't lost. A log is returned only if its
+//! block is still canonical, otherwise the logs of the canonical block are fetched instead. The
+//! logs removed by a reorg (`removed: true`) are dropped from the pending ones.
+//!
+//! The checkpoint is the first block whose logs haven't been handled yet. It's reported once all
+//! the logs before it have been returned and the next log is requested, which acknowledges that
+//! the returned ones are handled. So it can be persisted and the follower restarted from it, and a
+//! crash or a cancellation while a log is handled makes the restarted follower return it again
+//! instead of losing it.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use ethers::providers::{Middleware, Provider, PubsubClient, StreamExt, SubscriptionStream, Ws};
+use ethers::types::{BlockNumber, Filter, Log, H256, U256, U64};
+use eyre::{eyre, Context, Result};
+
+/// Interval between the checks of the pending logs' confirmations if no new logs arrive.
+const CONFIRMATIONS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Delay before resubscribing after the subscription has failed.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Callback persisting the checkpoint block.
+pub type OnCheckpoint<'a> = Box<dyn FnMut(u64) -> Result<()> + Send + 'a>;
+
+pub struct EventFollower<'a, P: PubsubClient = Ws> {
+    client: &'a Provider<P>,
+    logs: PendingLogs,
+    on_checkpoint: OnCheckpoint<'a>,
+
+    subscription: Option<SubscriptionStream<'a, P, Log>>,
+    confirmed: VecDeque<Log>,
+    /// The checkpoint that is reported once the [`Self::confirmed`] logs are handled.
+    checkpoint: Option<u64>,
+}
+
+impl<'a, P: PubsubClient> EventFollower<'a, P> {
+    /// Creates the follower of the logs matching the `filter` starting from `from_block`. The
+    /// filter's block range is ignored.
+    pub fn new(
+        client: &'a Provider<P>,
+        filter: Filter,
+        confirmations: u64,
+        from_block: u64,
+        on_checkpoint: OnCheckpoint<'a>,
+    ) -> Self {
+        Self {
+            client,
+            logs: PendingLogs::new(filter, confirmations, from_block),
+            on_checkpoint,
+            subscription: None,
+            confirmed: VecDeque::new(),
+            checkpoint: None,
+        }
+    }
+
+    /// Returns the next confirmed log. The call acknowledges that the logs returned before are
+    /// handled.
+    pub async fn next(&mut self) -> Result<Log> {
+        loop {
+            if let Some(log) = self.confirmed.pop_front() {
+                return Ok(log);
+            }
+
+            // All the logs before the checkpoint have been returned and handled.
+            if let Some(checkpoint) = self.checkpoint.take() {
+                (self.on_checkpoint)(checkpoint)
+                    .wrap_err("failed to persist Ethereum events checkpoint")?;
+            }
+
+            let checkpoint = self.logs.next_block;
+            if let Err(err) = self.poll().await {
+                println!("| Failed to follow Ethereum events, resubscribing: {err:#}");
+                self.subscription = None;
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+
+            if self.logs.next_block != checkpoint {
+                self.checkpoint = Some(self.logs.next_block);
+            }
+        }
+    }
+
+    /// Subscribes if needed, confirms the pending logs and waits for the new ones.
+    async fn poll(&mut self) -> Result<()> {
+        if self.subscription.is_none() {
+            let subscription = self
+                .client
+                .subscribe_logs(&self.logs.filter)
+                .await
+                .wrap_err("failed to subscribe to logs")?;
+            self.subscription = Some(subscription);
+        }
+
+        self.confirmed.extend(self.logs.confirm(self.client).await?);
+        if !self.confirmed.is_empty() {
+            return Ok(());
+        }
+
+        let subscription = self.subscription.as_mut().expect("subscribed above");
+        match tokio::time::timeout(CONFIRMATIONS_POLL_INTERVAL, subscription.next()).await {
+            Ok(Some(log)) => self.logs.add(log),
+            Ok(None) => return Err(eyre!("subscription has ended")),
+            // New blocks may have confirmed the pending logs.
+            Err(_elapsed) => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// The logs from the subscription waiting for the confirmations, and the checkpoint.
+struct PendingLogs {
+    filter: Filter,
+    confirmations: u64,
+    next_block: u64,
+
+    /// Logs by their block number, block hash and index.
+    pending: BTreeMap<(U64, H256, U256), Log>,
+}
+
+impl PendingLogs {
+    fn new(filter: Filter, confirmations: u64, from_block: u64) -> Self {
+        Self {
+            filter,
+            confirmations,
+            next_block: from_block,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, log: Log) {
+        // The logs of the pending blocks don't have a block yet.
+        let (Some(block_number), Some(block_hash), Some(log_index)) =
+            (log.block_number, log.block_hash, log.log_index)
+        else {
+            return;
+        };
+
+        let key = (block_number, block_hash, log_index);
+        if log.removed == Some(true) {
+            self.pending.remove(&key);
+        } else {
+            self.pending.insert(key, log);
+        }
+    }
+
+    /// Returns the logs of the blocks from the checkpoint up to the newly confirmed one and
+    /// advances the checkpoint past them. On failure, the checkpoint stays, so the logs are
+    /// fetched again on the next call.
+    async fn confirm<M: Middleware>(&mut self, client: &M) -> Result<Vec<Log>> {
+        let latest_block = client
+            .get_block_number()
+            .await
+            .map_err(|err| eyre!("failed to get Ethereum block number: {err}"))?
+            .as_u64();
+        let Some(confirmed_block) = (latest_block + 1).checked_sub(self.confirmations.max(1))
+        else {
+            return Ok(Vec::new());
+        };
+        if confirmed_block < self.next_block {
+            return Ok(Vec::new());
+        }
+
+        // The subscription may lag behind or have missed the logs, so the confirmed blocks are
+        // covered by `eth_getLogs` before the checkpoint moves past them.
+        let filter = self
+            .filter
+            .clone()
+            .from_block(self.next_block)
+            .to_block(confirmed_block);
+        for log in client
+            .get_logs(&filter)
+            .await
+            .map_err(|err| eyre!("failed to get Ethereum logs: {err}"))?
+        {
+            self.add(log);
+        }
+
+        let unconfirmed =
+            self.pending
+                .split_off(&(U64::from(confirmed_block + 1), H256::zero(), U256::zero()));
+        let ready = std::mem::replace(&mut self.pending, unconfirmed);
+
+        // The logs of the blocks before the checkpoint have been returned already.
+        let mut blocks = BTreeMap::<U64, Vec<Log>>::new();
+        for ((block_number, _, _), log) in ready {
+            if block_number.as_u64() >= self.next_block {
+                blocks.entry(block_number).or_default().push(log);
+            }
+        }
+
+        let mut confirmed = Vec::new();
+        for (block_number, logs) in blocks {
+            let canonical_hash = client
+                .get_block(BlockNumber::Number(block_number))
+                .await
+                .map_err(|err| eyre!("failed to get Ethereum block {block_number}: {err}"))?
+                .and_then(|block| block.hash)
+                .ok_or_else(|| eyre!("Ethereum block {block_number} is absent"))?;
+
+            let canonical_logs: Vec<_> = logs
+                .into_iter()
+                .filter(|log| log.block_hash == Some(canonical_hash))
+                .collect();
+            if !canonical_logs.is_empty() {
+                confirmed.extend(canonical_logs);
+                continue;
+            }
+
+            // The block has been reorged out after the logs were fetched.
+            let filter = self.filter.clone().at_block_hash(canonical_hash);
+            confirmed.extend(
+                client
+                    .get_logs(&filter)
+                    .await
+                    .map_err(|err| eyre!("failed to get Ethereum logs: {err}"))?,
+            );
+        }
+
+        self.next_block = confirmed_block + 1;
+
+        Ok(confirmed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use ethers::providers::{JsonRpcClient, MockError, MockProvider, Provider, PubsubClient};
+    use ethers::types::{Block, Filter, Log, TxHash, H256, U256, U64};
+    use futures_util::stream::{self, Pending};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::value::RawValue;
+
+    use super::{EventFollower, PendingLogs};
+
+    /// The mocked client whose subscription never delivers a log, so the logs come from
+    /// `eth_getLogs`.
+    #[derive(Debug)]
+    struct MockPubsub(MockProvider);
+
+    #[async_trait]
+    impl JsonRpcClient for MockPubsub {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            self.0.request(method, params).await
+        }
+    }
+
+    impl PubsubClient for MockPubsub {
+        type NotificationStream = Pending<Box<RawValue>>;
+
+        fn subscribe<T: Into<U256>>(&self, _id: T) -> Result<Self::NotificationStream, MockError> {
+            Ok(stream::pending())
+        }
+
+        fn unsubscribe<T: Into<U256>>(&self, _id: T) -> Result<(), MockError> {
+            Ok(())
+        }
+    }
+
+    fn log(block_number: u64, block_hash: H256, log_index: u64) -> Log {
+        Log {
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash),
+            log_index: Some(log_index.into()),
+            removed: Some(false),
+            ..Default::default()
+        }
+    }
+
+    fn block(block_number: u64, block_hash: H256) -> Block<TxHash> {
+        Block {
+            number: Some(block_number.into()),
+            hash: Some(block_hash),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_log() {
+        let (client, mock) = Provider::mocked();
+        let mut logs = PendingLogs::new(Filter::new(), 2, 10);
+        let hash = H256::repeat_byte(1);
+
+        // The subscription hasn't delivered the log of block 10 yet, but `eth_getLogs` has.
+        // The mocked responses are returned in the reverse order.
+        mock.push::<Block<TxHash>, _>(block(10, hash)).unwrap();
+        mock.push::<Vec<Log>, _>(vec![log(10, hash, 0)]).unwrap();
+        mock.push::<U64, _>(U64::from(11)).unwrap();
+
+        assert_eq!(logs.confirm(&client).await.unwrap(), [log(10, hash, 0)]);
+        assert_eq!(logs.next_block, 11);
+
+        // The late log from the subscription isn't returned again.
+        logs.add(log(10, hash, 0));
+        mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+        mock.push::<U64, _>(U64::from(12)).unwrap();
+
+        assert!(logs.confirm(&client).await.unwrap().is_empty());
+        assert_eq!(logs.next_block, 12);
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_log() {
+        let (client, mock) = Provider::mocked();
+        let mut logs = PendingLogs::new(Filter::new(), 3, 10);
+        let hash = H256::repeat_byte(1);
+
+        logs.add(log(11, hash, 0));
+
+        // Block 10 is confirmed, but block 11 isn't yet.
+        mock.push::<Vec<Log>, _>(vec![log(11, hash, 0)]).unwrap();
+        mock.push::<U64, _>(U64::from(12)).unwrap();
+
+        assert!(logs.confirm(&client).await.unwrap().is_empty());
+        assert_eq!(logs.next_block, 11);
+        assert_eq!(logs.pending.len(), 1);
+
+        mock.push::<U64, _>(U64::from(12)).unwrap();
+        assert!(logs.confirm(&client).await.unwrap().is_empty());
+        assert_eq!(logs.next_block, 11);
+    }
+
+    #[tokio::test]
+    async fn test_removed_log() {
+        let (client, mock) = Provider::mocked();
+        let mut logs = PendingLogs::new(Filter::new(), 2, 10);
+        let hash = H256::repeat_byte(1);
+
+        logs.add(log(10, hash, 0));
+        logs.add(Log {
+            removed: Some(true),
+            ..log(10, hash, 0)
+        });
+        assert!(logs.pending.is_empty());
+
+        mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+        mock.push::<U64, _>(U64::from(11)).unwrap();
+
+        assert!(logs.confirm(&client).await.unwrap().is_empty());
+        assert_eq!(logs.next_block, 11);
+    }
+
+    #[tokio::test]
+    async fn test_reorged_block() {
+        let (client, mock) = Provider::mocked();
+        let mut logs = PendingLogs::new(Filter::new(), 2, 10);
+        let reorged_hash = H256::repeat_byte(1);
+        let canonical_hash = H256::repeat_byte(2);
+
+        // The log of the reorged block is dropped, and the one of the canonical block is returned.
+        logs.add(log(10, reorged_hash, 0));
+        mock.push::<Block<TxHash>, _>(block(10, canonical_hash))
+            .unwrap();
+        mock.push::<Vec<Log>, _>(vec![log(10, canonical_hash, 1)])
+            .unwrap();
+        mock.push::<U64, _>(U64::from(11)).unwrap();
+
+        assert_eq!(
+            logs.confirm(&client).await.unwrap(),
+            [log(10, canonical_hash, 1)]
+        );
+
+        // If the block is reorged again after `eth_getLogs`, the logs are fetched by the hash of
+        // the canonical block.
+        let mut logs = PendingLogs::new(Filter::new(), 2, 10);
+        mock.push::<Vec<Log>, _>(Vec::new()).unwrap();
+        mock.push::<Block<TxHash>, _>(block(10, canonical_hash))
+            .unwrap();
+        mock.push::<Vec<Log>, _>(vec![log(10, reorged_hash, 0)])
+            .unwrap();
+        mock.push::<U64, _>(U64::from(11)).unwrap();
+
+        assert!(logs.confirm(&client).await.unwrap().is_empty());
+        assert_eq!(logs.next_block, 11);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_after_handled_logs() {
+        let mock = MockProvider::new();
+        let client = Provider::new(MockPubsub(mock.clone()));
+        let hash = H256::repeat_byte(1);
+
+        let checkpoints = Arc::new(Mutex::new(Vec::new()));
+        let persisted_checkpoints = checkpoints.clone();
+        let mut follower = EventFollower::new(
+            &client,
+            Filter::new(),
+            2,
+            10,
+            Box::new(move |block| {
+                persisted_checkpoints.lock().unwrap().push(block);
+                Ok(())
+            }),
+        );
+
+        // Blocks 10 and 11 are confirmed, and the latter has two logs.
+        mock.push::<Block<TxHash>, _>(block(11, hash)).unwrap();
+        mock.push::<Vec<Log>, _>(vec![log(11, hash, 0), log(11, hash, 1)])
+            .unwrap();
+        mock.push::<U64, _>(U64::from(12)).unwrap();
+        mock.push::<U256, _>(U256::one()).unwrap();
+
+        assert_eq!(follower.next().await.unwrap(), log(11, hash, 0));
+        assert_eq!(follower.next().await.unwrap(), log(11, hash, 1));
+        // The last log may be still being handled, so the checkpoint past it isn't persisted yet.
+        assert!(checkpoints.lock().unwrap().is_empty());
+
+        // Requesting the next log acknowledges it. There are no more mocked responses, so the
+        // follower keeps retrying.
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), follower.next())
+                .await
+                .is_err()
+        );
+        assert_eq!(*checkpoints.lock().unwrap(), [12]);
+    }
+}
@@ -0,0 +1,491 @@
+//! Gas strategy of the `Depositor` calls.
+//!
+//! The calls are sent as EIP-1559 transactions with the fees estimated by the node and capped by
+//! the config, the gas limit estimated with a margin, and the nonce of the pending block, so a
+//! replacement can reuse it. The withdraw must be mined before the deposit's lock time, after which
+//! the depositor can restore the deposit, so while it's pending too close to the lock time, it's
+//! resubmitted with bumped fees. Past the lock time, a submission may still be mined until the
+//! deposit is restored, so it's watched until it's mined or its nonce is taken by another one.
+
+use std::time::Duration;
+
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockNumber, TxHash, U256};
+use ethers::utils::Units::Gwei;
+use eyre::{eyre, Context, Result};
+
+/// Minimal fee bump the nodes accept to replace a pending transaction.
+const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct GasConfig {
+    /// Cap of the max fee per gas in gwei.
+    pub max_fee_per_gas_gwei: Option<u64>,
+    /// Cap of the max priority fee per gas in gwei.
+    pub max_priority_fee_per_gas_gwei: Option<u64>,
+    /// Margin added to the estimated gas limit in percent.
+    pub gas_limit_margin_percent: u64,
+    /// The withdraw that's still pending this number of seconds before the deposit's lock time is
+    /// resubmitted with bumped fees.
+    pub withdraw_deadline_margin_secs: u64,
+    /// Increase of both the fees on resubmission in percent, at least 10.
+    pub fee_bump_percent: u64,
+    /// Whether the fees are bumped without `max_fee_per_gas_gwei`. They aren't by default, so a
+    /// stuck withdraw can't spend the whole balance on fees.
+    pub uncapped_fee_bumps: bool,
+    /// Interval between the checks if the withdraw has been mined.
+    pub resubmit_interval_secs: u64,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            max_fee_per_gas_gwei: None,
+            max_priority_fee_per_gas_gwei: None,
+            gas_limit_margin_percent: 20,
+            withdraw_deadline_margin_secs: 600,
+            fee_bump_percent: 20,
+            uncapped_fee_bumps: false,
+            resubmit_interval_secs: 15,
+        }
+    }
+}
+
+impl GasConfig {
+    /// Fills the fees, the gas limit and the nonce of the transaction, and sends it.
+    pub async fn send_transaction<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut tx: TypedTransaction,
+    ) -> Result<TxHash> {
+        self.fill_transaction(client, &mut tx).await?;
+
+        let pending_tx = client
+            .send_transaction(tx, None)
+            .await
+            .wrap_err("failed to send Ethereum transaction")?;
+
+        Ok(pending_tx.tx_hash())
+    }
+
+    /// Sends the transaction and waits for it to be mined. If it's still pending
+    /// `withdraw_deadline_margin_secs` before the `deadline` UNIX timestamp, it's resubmitted
+    /// with the same nonce and bumped fees, at most once per block, until one of the submissions
+    /// is mined. After the `deadline`, it isn't resubmitted anymore, but the submissions are
+    /// watched until one of them is mined, or fails if another transaction takes their nonce.
+    pub async fn send_transaction_before<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut tx: TypedTransaction,
+        deadline: U256,
+    ) -> Result<TxHash> {
+        self.fill_transaction(client, &mut tx).await?;
+
+        let pending_tx = client
+            .send_transaction(tx.clone(), None)
+            .await
+            .wrap_err("failed to send Ethereum transaction")?;
+
+        self.wait_or_resubmit(client, tx, pending_tx.tx_hash(), deadline)
+            .await
+    }
+
+    /// Waits for the sent `tx` to be mined, resubmitting it as [`Self::send_transaction_before`]
+    /// describes.
+    async fn wait_or_resubmit<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut tx: TypedTransaction,
+        tx_hash: TxHash,
+        deadline: U256,
+    ) -> Result<TxHash> {
+        let mut tx_hashes = vec![tx_hash];
+        let (Some(from), Some(nonce)) = (tx.from().copied(), tx.nonce().copied()) else {
+            return Err(eyre!("transaction sender or nonce is absent"));
+        };
+
+        // Without a cap, the fees would be bumped until the deposit is spent on them.
+        let mut can_bump = self.max_fee_per_gas_gwei.is_some() || self.uncapped_fee_bumps;
+        let bump_after = deadline.saturating_sub(self.withdraw_deadline_margin_secs.into());
+        let mut last_bump_block = None;
+        let mut is_past_deadline = false;
+        loop {
+            tokio::time::sleep(Duration::from_secs(self.resubmit_interval_secs)).await;
+
+            // The nonce is read before the receipts, so a submission that is mined in between
+            // isn't taken for a replacement.
+            let mined_nonce = if is_past_deadline {
+                Some(
+                    client
+                        .get_transaction_count(from, Some(BlockNumber::Latest.into()))
+                        .await
+                        .wrap_err("failed to get Ethereum transaction count")?,
+                )
+            } else {
+                None
+            };
+
+            for tx_hash in &tx_hashes {
+                let Some(receipt) = client
+                    .get_transaction_receipt(*tx_hash)
+                    .await
+                    .wrap_err("failed to get Ethereum transaction receipt")?
+                else {
+                    continue;
+                };
+
+                if receipt.status != Some(1.into()) {
+                    return Err(eyre!("Ethereum transaction {tx_hash:?} has been reverted"));
+                }
+
+                return Ok(*tx_hash);
+            }
+
+            if let Some(mined_nonce) = mined_nonce {
+                if mined_nonce > nonce {
+                    return Err(eyre!(
+                        "Ethereum transaction {:?} hasn't been mined before the deadline and has \
+                         been replaced",
+                        tx_hashes[0]
+                    ));
+                }
+                continue;
+            }
+
+            let block = client
+                .get_block(BlockNumber::Latest)
+                .await
+                .wrap_err("failed to get latest Ethereum block")?
+                .ok_or_else(|| eyre!("latest Ethereum block is absent"))?;
+            if block.timestamp >= deadline {
+                println!(
+                    "| Transaction {:?} hasn't been mined before the deadline, waiting until it's \
+                     mined or replaced",
+                    tx_hashes[0]
+                );
+                is_past_deadline = true;
+                continue;
+            }
+            if block.timestamp < bump_after || !can_bump || block.number <= last_bump_block {
+                continue;
+            }
+
+            let Some((max_fee, priority_fee)) = self.bumped_fees(&tx)? else {
+                println!("| Pending transaction is close to the lock time, but fees are capped");
+                // The caps don't change, so there's nothing to bump until the deadline.
+                can_bump = false;
+                continue;
+            };
+            set_fees(&mut tx, max_fee, priority_fee)?;
+            last_bump_block = block.number;
+
+            match client.send_transaction(tx.clone(), None).await {
+                Ok(pending_tx) => {
+                    println!(
+                        "| Pending transaction is close to the lock time, resubmitted with max fee \
+                         {max_fee} wei: {:?}",
+                        pending_tx.tx_hash()
+                    );
+                    tx_hashes.push(pending_tx.tx_hash());
+                }
+                // The previous submission may have been mined in the meantime, that's checked
+                // on the next iteration.
+                Err(err) => println!("| Failed to resubmit transaction: {err}"),
+            }
+        }
+    }
+
+    /// Sets the capped EIP-1559 fees, the pending nonce and the estimated gas limit with the
+    /// margin.
+    async fn fill_transaction<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        tx: &mut TypedTransaction,
+    ) -> Result<()> {
+        let (max_fee, priority_fee) = client
+            .estimate_eip1559_fees(None)
+            .await
+            .wrap_err("failed to estimate Ethereum fees")?;
+        let (max_fee, priority_fee) = self.capped_fees(max_fee, priority_fee);
+        set_fees(tx, max_fee, priority_fee)?;
+
+        client
+            .fill_transaction(tx, None)
+            .await
+            .wrap_err("failed to estimate Ethereum transaction")?;
+
+        let from = *tx
+            .from()
+            .ok_or_else(|| eyre!("transaction sender is absent"))?;
+        let nonce = client
+            .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+            .await
+            .wrap_err("failed to get Ethereum nonce")?;
+        tx.set_nonce(nonce);
+
+        let gas = tx
+            .gas()
+            .copied()
+            .ok_or_else(|| eyre!("transaction gas limit is absent"))?;
+        tx.set_gas(gas * (100 + self.gas_limit_margin_percent) / 100);
+
+        Ok(())
+    }
+
+    fn capped_fees(&self, max_fee: U256, priority_fee: U256) -> (U256, U256) {
+        let max_fee = cap(max_fee, self.max_fee_per_gas_gwei);
+        let priority_fee = cap(priority_fee, self.max_priority_fee_per_gas_gwei).min(max_fee);
+
+        (max_fee, priority_fee)
+    }
+
+    /// Returns the bumped fees of the transaction, or [`None`] if the caps don't allow to bump
+    /// them enough for a replacement.
+    fn bumped_fees(&self, tx: &TypedTransaction) -> Result<Option<(U256, U256)>> {
+        let TypedTransaction::Eip1559(tx) = tx else {
+            return Err(eyre!("transaction is not an EIP-1559 one"));
+        };
+        let (Some(max_fee), Some(priority_fee)) = (tx.max_fee_per_gas, tx.max_priority_fee_per_gas)
+        else {
+            return Err(eyre!("transaction fees are absent"));
+        };
+
+        let bump = |fee: U256| fee * (100 + self.fee_bump_percent.max(MIN_FEE_BUMP_PERCENT)) / 100;
+        let (bumped_max_fee, bumped_priority_fee) =
+            self.capped_fees(bump(max_fee), bump(priority_fee));
+
+        let min_bump = |fee: U256| fee * (100 + MIN_FEE_BUMP_PERCENT) / 100;
+        if bumped_max_fee < min_bump(max_fee) || bumped_priority_fee < min_bump(priority_fee) {
+            return Ok(None);
+        }
+
+        Ok(Some((bumped_max_fee, bumped_priority_fee)))
+    }
+}
+
+fn cap(fee: U256, cap_gwei: Option<u64>) -> U256 {
+    match cap_gwei {
+        Some(cap_gwei) => fee.min(U256::from(cap_gwei) * U256::exp10(Gwei.as_num() as usize)),
+        None => fee,
+    }
+}
+
+fn set_fees(tx: &mut TypedTransaction, max_fee: U256, priority_fee: U256) -> Result<()> {
+    let TypedTransaction::Eip1559(tx) = tx else {
+        return Err(eyre!("transaction is not an EIP-1559 one"));
+    };
+    tx.max_fee_per_gas = Some(max_fee);
+    tx.max_priority_fee_per_gas = Some(priority_fee);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::providers::Provider;
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{
+        Address, Block, Eip1559TransactionRequest, TransactionReceipt, TxHash, U256,
+    };
+
+    use super::GasConfig;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn transaction(max_fee: u64, priority_fee: u64) -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(Address::repeat_byte(2))
+            .gas(100_000)
+            .nonce(0)
+            .max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(priority_fee)
+            .into()
+    }
+
+    fn block(number: u64, timestamp: u64) -> Block<TxHash> {
+        Block {
+            number: Some(number.into()),
+            timestamp: timestamp.into(),
+            ..Default::default()
+        }
+    }
+
+    fn receipt(tx_hash: TxHash) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: tx_hash,
+            status: Some(1.into()),
+            ..Default::default()
+        }
+    }
+
+    fn config() -> GasConfig {
+        GasConfig {
+            max_fee_per_gas_gwei: Some(1000),
+            withdraw_deadline_margin_secs: 600,
+            resubmit_interval_secs: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_capped_fees() {
+        let config = GasConfig {
+            max_fee_per_gas_gwei: Some(50),
+            max_priority_fee_per_gas_gwei: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.capped_fees(U256::from(40 * GWEI), U256::from(GWEI)),
+            (U256::from(40 * GWEI), U256::from(GWEI))
+        );
+        assert_eq!(
+            config.capped_fees(U256::from(80 * GWEI), U256::from(3 * GWEI)),
+            (U256::from(50 * GWEI), U256::from(2 * GWEI))
+        );
+
+        let config = GasConfig {
+            max_fee_per_gas_gwei: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.capped_fees(U256::from(80 * GWEI), U256::from(3 * GWEI)),
+            (U256::from(GWEI), U256::from(GWEI))
+        );
+    }
+
+    #[test]
+    fn test_bumped_fees() {
+        let config = GasConfig {
+            fee_bump_percent: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            config
+                .bumped_fees(&transaction(100 * GWEI, 10 * GWEI))
+                .unwrap(),
+            Some((U256::from(110 * GWEI), U256::from(11 * GWEI)))
+        );
+
+        let config = GasConfig {
+            max_fee_per_gas_gwei: Some(105),
+            ..Default::default()
+        };
+        assert_eq!(
+            config
+                .bumped_fees(&transaction(100 * GWEI, 10 * GWEI))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let (client, mock) = Provider::mocked();
+        let tx_hash = TxHash::repeat_byte(1);
+
+        // After the deadline, the transaction isn't resubmitted, but it's watched until it's
+        // mined. The mocked responses are returned in the reverse order.
+        mock.push::<Option<TransactionReceipt>, _>(Some(receipt(tx_hash)))
+            .unwrap();
+        mock.push::<U256, _>(U256::from(1)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<U256, _>(U256::zero()).unwrap();
+        mock.push::<Block<TxHash>, _>(block(11, 1000)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+
+        assert_eq!(
+            config()
+                .wait_or_resubmit(
+                    &client,
+                    transaction(100 * GWEI, 10 * GWEI),
+                    tx_hash,
+                    U256::from(1000),
+                )
+                .await
+                .unwrap(),
+            tx_hash
+        );
+
+        // It fails once another transaction takes the nonce.
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<U256, _>(U256::from(1)).unwrap();
+        mock.push::<Block<TxHash>, _>(block(11, 1000)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+
+        let err = config()
+            .wait_or_resubmit(
+                &client,
+                transaction(100 * GWEI, 10 * GWEI),
+                tx_hash,
+                U256::from(1000),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("replaced"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_resubmit_once_per_block() {
+        let (client, mock) = Provider::mocked();
+        let tx_hash = TxHash::repeat_byte(1);
+        let bumped_tx_hash = TxHash::repeat_byte(2);
+
+        // The block is past the deadline margin, so the transaction is resubmitted, but only once
+        // in the block.
+        mock.push::<Option<TransactionReceipt>, _>(Some(receipt(bumped_tx_hash)))
+            .unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<Block<TxHash>, _>(block(10, 500)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<TxHash, _>(bumped_tx_hash).unwrap();
+        mock.push::<Block<TxHash>, _>(block(10, 500)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+
+        assert_eq!(
+            config()
+                .wait_or_resubmit(
+                    &client,
+                    transaction(100 * GWEI, 10 * GWEI),
+                    tx_hash,
+                    U256::from(1000),
+                )
+                .await
+                .unwrap(),
+            bumped_tx_hash
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_uncapped_resubmit() {
+        let (client, mock) = Provider::mocked();
+        let config = GasConfig {
+            max_fee_per_gas_gwei: None,
+            ..config()
+        };
+
+        // Without the cap and the opt-in, the fees aren't bumped and the transaction isn't
+        // resubmitted.
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<U256, _>(U256::from(1)).unwrap();
+        mock.push::<Block<TxHash>, _>(block(11, 1000)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        mock.push::<Block<TxHash>, _>(block(10, 500)).unwrap();
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+
+        let err = config
+            .wait_or_resubmit(
+                &client,
+                transaction(100 * GWEI, 10 * GWEI),
+                TxHash::repeat_byte(1),
+                U256::from(1000),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("replaced"), "{err}");
+    }
+}
@@ -0,0 +1,185 @@
+//! Participant keys derived from a BIP39 mnemonic.
+//!
+//! The funding wallets use the standard accounts, so the same mnemonic can be restored in other
+//! wallets:
+//! - Bitcoin: BIP86 `m/86'/coin'/0'/{0,1}/*`;
+//! - Ethereum: BIP44 `m/44'/60'/0'/0/0`.
+//!
+//! Every swap uses fresh keys from account `1'` at its own index, so swaps by one participant
+//! can't be linked by their keys or addresses:
+//! - the Bitcoin swap key, that is the revocation key of the initiator and the counterparty's part
+//!   of the escrow key, is `m/86'/coin'/1'/0/index`;
+//! - the Bitcoin redeem address is the BIP86 address of `m/86'/coin'/1'/1/index`;
+//! - the Ethereum address that receives the deposit is `m/44'/60'/1'/0/index`.
+
+use std::str::FromStr;
+
+use bdk::bitcoin::secp256k1::{All, KeyPair, Secp256k1, SecretKey};
+use bdk::bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+use bdk::bitcoin::{Address as BitcoinAddress, Network as BitcoinNetwork};
+use bdk::keys::bip39::Mnemonic;
+use eyre::{Context, Result};
+
+/// BIP86 purpose of the single-key P2TR derivation.
+const BIP86_PURPOSE: u32 = 86;
+
+/// BIP44 purpose and the SLIP-44 coin type of Ether.
+const BIP44_PURPOSE: u32 = 44;
+const ETHEREUM_COIN_TYPE: u32 = 60;
+
+/// Account of the funding wallets.
+const FUNDING_ACCOUNT: u32 = 0;
+
+/// Account of the per-swap keys.
+const SWAP_ACCOUNT: u32 = 1;
+
+/// The BIP32 master key derived from the mnemonic.
+pub struct HdKeys {
+    master: ExtendedPrivKey,
+    network: BitcoinNetwork,
+}
+
+/// Keys of a single swap.
+pub struct SwapKeys {
+    pub bitcoin: SecretKey,
+    pub bitcoin_redeem_address: BitcoinAddress,
+    /// The key of the address the deposit is made for. It's [`None`] for the single keys without
+    /// the Ethereum key, then the signer's address is used.
+    pub ethereum: Option<SecretKey>,
+}
+
+impl HdKeys {
+    pub fn from_mnemonic(mnemonic: &str, network: BitcoinNetwork) -> Result<Self> {
+        let mnemonic = Mnemonic::parse(mnemonic).wrap_err("failed to parse BIP39 mnemonic")?;
+        let master = ExtendedPrivKey::new_master(network, &mnemonic.to_seed(""))
+            .wrap_err("failed to derive BIP32 master key")?;
+
+        Ok(Self { master, network })
+    }
+
+    /// Returns the external and internal descriptors of the BIP86 funding wallet.
+    pub fn bitcoin_wallet_descriptors(&self) -> (String, String) {
+        let account = format!(
+            "{}/{BIP86_PURPOSE}'/{}'/{FUNDING_ACCOUNT}'",
+            self.master,
+            self.bitcoin_coin_type()
+        );
+
+        (format!("tr({account}/0/*)"), format!("tr({account}/1/*)"))
+    }
+
+    /// Returns the key of the Ethereum funding account that pays for the transactions.
+    pub fn ethereum_funding_key(&self, secp_ctx: &Secp256k1<All>) -> Result<SecretKey> {
+        self.derive(
+            secp_ctx,
+            &format!("m/{BIP44_PURPOSE}'/{ETHEREUM_COIN_TYPE}'/{FUNDING_ACCOUNT}'/0/0"),
+        )
+    }
+
+    pub fn swap_keys(&self, secp_ctx: &Secp256k1<All>, index: u32) -> Result<SwapKeys> {
+        let coin_type = self.bitcoin_coin_type();
+
+        let bitcoin = self.derive(
+            secp_ctx,
+            &format!("m/{BIP86_PURPOSE}'/{coin_type}'/{SWAP_ACCOUNT}'/0/{index}"),
+        )?;
+
+        let redeem_key = self.derive(
+            secp_ctx,
+            &format!("m/{BIP86_PURPOSE}'/{coin_type}'/{SWAP_ACCOUNT}'/1/{index}"),
+        )?;
+        let bitcoin_redeem_address = BitcoinAddress::p2tr(
+            secp_ctx,
+            KeyPair::from_secret_key(secp_ctx, &redeem_key)
+                .x_only_public_key()
+                .0,
+            None,
+            self.network,
+        );
+
+        let ethereum = self.derive(
+            secp_ctx,
+            &format!("m/{BIP44_PURPOSE}'/{ETHEREUM_COIN_TYPE}'/{SWAP_ACCOUNT}'/0/{index}"),
+        )?;
+
+        Ok(SwapKeys {
+            bitcoin,
+            bitcoin_redeem_address,
+            ethereum: Some(ethereum),
+        })
+    }
+
+    fn derive(&self, secp_ctx: &Secp256k1<All>, path: &str) -> Result<SecretKey> {
+        let path = DerivationPath::from_str(path).wrap_err("invalid derivation path")?;
+
+        let key = self
+            .master
+            .derive_priv(secp_ctx, &path)
+            .wrap_err_with(|| format!("failed to derive key {path}"))?;
+
+        Ok(key.private_key)
+    }
+
+    /// SLIP-44 coin type: `0'` for mainnet and `1'` for the test networks.
+    fn bitcoin_coin_type(&self) -> u32 {
+        match self.network {
+            BitcoinNetwork::Bitcoin => 0,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::bitcoin::secp256k1::{KeyPair, Secp256k1};
+    use bdk::bitcoin::{Address as BitcoinAddress, Network as BitcoinNetwork};
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::Address as EthereumAddress;
+
+    use super::HdKeys;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                            abandon abandon abandon about";
+
+    #[test]
+    fn test_derivation_vectors() {
+        let secp_ctx = Secp256k1::new();
+        let keys = HdKeys::from_mnemonic(MNEMONIC, BitcoinNetwork::Bitcoin).unwrap();
+
+        // BIP86 test vector: m/86'/0'/0'/0/0
+        let key = keys.derive(&secp_ctx, "m/86'/0'/0'/0/0").unwrap();
+        let internal_key = KeyPair::from_secret_key(&secp_ctx, &key)
+            .x_only_public_key()
+            .0;
+        let address = BitcoinAddress::p2tr(&secp_ctx, internal_key, None, keys.network);
+        assert_eq!(
+            address.to_string(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+
+        // The first MetaMask account of the mnemonic.
+        let key = keys.ethereum_funding_key(&secp_ctx).unwrap();
+        let wallet = LocalWallet::from_bytes(&key.secret_bytes()).unwrap();
+        assert_eq!(
+            wallet.address(),
+            "0x9858EfFD232B4033E47d90003D41EC34EcaEda94"
+                .parse::<EthereumAddress>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_swap_keys_are_fresh() {
+        let secp_ctx = Secp256k1::new();
+        let keys = HdKeys::from_mnemonic(MNEMONIC, BitcoinNetwork::Regtest).unwrap();
+
+        let first = keys.swap_keys(&secp_ctx, 0).unwrap();
+        let second = keys.swap_keys(&secp_ctx, 1).unwrap();
+        assert_ne!(first.bitcoin, second.bitcoin);
+        assert_ne!(first.bitcoin_redeem_address, second.bitcoin_redeem_address);
+        assert_ne!(first.ethereum, second.ethereum);
+
+        let funding = keys.ethereum_funding_key(&secp_ctx).unwrap();
+        assert_ne!(first.ethereum, Some(funding));
+    }
+}
@@ -0,0 +1,184 @@
+//! Encrypted private keys.
+//!
+//! The keys are stored in the Web3 Secret Storage format (scrypt and AES-128-CTR), the JSON
+//! keystore `geth`, `cast wallet` and ethers use. The Ethereum keystore is decrypted with ethers,
+//! and the Bitcoin key is encrypted into the same format by `encrypt-key`, as it's a secp256k1
+//! secret key as well. The BIP39 mnemonic is encrypted by `encrypt-mnemonic` the same way.
+//!
+//! The passphrase is read from the environment variable set in the config, or prompted for
+//! otherwise.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use bdk::bitcoin::secp256k1::SecretKey;
+use bdk::keys::bip39::Mnemonic;
+use ethers::signers::LocalWallet;
+use eyre::{eyre, Context, Result};
+use zeroize::Zeroizing;
+
+/// Decrypts the Ethereum JSON keystore.
+pub fn decrypt_ethereum_key(path: &Path, passphrase: &str) -> Result<SecretKey> {
+    let wallet = LocalWallet::decrypt_keystore(path, passphrase).wrap_err_with(|| {
+        format!(
+            "failed to decrypt Ethereum keystore {}, the passphrase may be wrong",
+            path.display()
+        )
+    })?;
+
+    SecretKey::from_slice(&wallet.signer().to_bytes())
+        .wrap_err("Ethereum keystore contains invalid secret key")
+}
+
+/// Decrypts the Bitcoin key file produced by `encrypt-key`.
+pub fn decrypt_bitcoin_key(path: &Path, passphrase: &str) -> Result<SecretKey> {
+    let bytes = Zeroizing::new(eth_keystore::decrypt_key(path, passphrase).wrap_err_with(
+        || {
+            format!(
+                "failed to decrypt Bitcoin keystore {}, the passphrase may be wrong",
+                path.display()
+            )
+        },
+    )?);
+
+    SecretKey::from_slice(&bytes).wrap_err("Bitcoin keystore contains invalid secret key")
+}
+
+/// Decrypts the mnemonic file produced by `encrypt-mnemonic`.
+pub fn decrypt_mnemonic(path: &Path, passphrase: &str) -> Result<Zeroizing<String>> {
+    let bytes = Zeroizing::new(eth_keystore::decrypt_key(path, passphrase).wrap_err_with(
+        || {
+            format!(
+                "failed to decrypt mnemonic keystore {}, the passphrase may be wrong",
+                path.display()
+            )
+        },
+    )?);
+
+    let mnemonic =
+        std::str::from_utf8(&bytes).wrap_err("mnemonic keystore contains invalid UTF-8")?;
+
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Encrypts the secret key into a keystore at `path`.
+pub fn encrypt_key(path: &Path, secret_key: &SecretKey, passphrase: &str) -> Result<()> {
+    encrypt_secret(path, &secret_key.secret_bytes(), passphrase)
+}
+
+/// Encrypts the mnemonic into a keystore at `path`.
+pub fn encrypt_mnemonic(path: &Path, mnemonic: &str, passphrase: &str) -> Result<()> {
+    encrypt_secret(path, mnemonic.as_bytes(), passphrase)
+}
+
+fn encrypt_secret(path: &Path, secret: &[u8], passphrase: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| eyre!("invalid keystore path: {}", path.display()))?;
+
+    eth_keystore::encrypt_key(dir, &mut rand::thread_rng(), secret, passphrase, Some(name))
+        .wrap_err("failed to encrypt key")?;
+
+    Ok(())
+}
+
+/// Returns the passphrase from the `env` variable if it's set, or prompts for it.
+pub fn read_passphrase(env: Option<&str>, prompt: &str) -> Result<Zeroizing<String>> {
+    if let Some(passphrase) = env.and_then(|env| std::env::var(env).ok()) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+
+    rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .wrap_err("failed to read passphrase")
+}
+
+/// Prompts for the hex-encoded secret key and the passphrase, and writes the keystore to `path`.
+pub fn encrypt_key_interactive(path: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(eyre!("{} already exists", path.display()));
+    }
+
+    let secret_key = Zeroizing::new(rpassword::prompt_password("Hex-encoded private key: ")?);
+    let secret_key = SecretKey::from_str(secret_key.trim_start_matches("0x"))
+        .wrap_err("failed to parse private key")?;
+
+    encrypt_key(path, &secret_key, &prompt_new_passphrase()?)?;
+    println!("Keystore is written to {}", path.display());
+
+    Ok(())
+}
+
+/// Prompts for the BIP39 mnemonic and the passphrase, and writes the keystore to `path`.
+pub fn encrypt_mnemonic_interactive(path: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(eyre!("{} already exists", path.display()));
+    }
+
+    let mnemonic = Zeroizing::new(rpassword::prompt_password("BIP39 mnemonic: ")?);
+    let mnemonic = Zeroizing::new(mnemonic.split_whitespace().collect::<Vec<_>>().join(" "));
+    Mnemonic::parse(mnemonic.as_str()).wrap_err("failed to parse BIP39 mnemonic")?;
+
+    encrypt_mnemonic(path, &mnemonic, &prompt_new_passphrase()?)?;
+    println!("Keystore is written to {}", path.display());
+
+    Ok(())
+}
+
+fn prompt_new_passphrase() -> Result<Zeroizing<String>> {
+    let passphrase = rpassword::prompt_password("Passphrase: ").map(Zeroizing::new)?;
+    let confirmation = rpassword::prompt_password("Repeat passphrase: ").map(Zeroizing::new)?;
+    if passphrase != confirmation {
+        return Err(eyre!("passphrases don't match"));
+    }
+
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::bitcoin::secp256k1::SecretKey;
+
+    use super::{
+        decrypt_bitcoin_key, decrypt_ethereum_key, decrypt_mnemonic, encrypt_key, encrypt_mnemonic,
+    };
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let path = std::env::temp_dir().join(format!("{}-keystore.json", std::process::id()));
+
+        encrypt_key(&path, &secret_key, "passphrase").unwrap();
+
+        assert_eq!(
+            decrypt_bitcoin_key(&path, "passphrase").unwrap(),
+            secret_key
+        );
+        assert_eq!(
+            decrypt_ethereum_key(&path, "passphrase").unwrap(),
+            secret_key
+        );
+        assert!(decrypt_bitcoin_key(&path, "wrong passphrase").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_mnemonic_keystore_roundtrip() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                        abandon abandon about";
+        let path = std::env::temp_dir().join(format!("{}-mnemonic.json", std::process::id()));
+
+        encrypt_mnemonic(&path, mnemonic, "passphrase").unwrap();
+
+        assert_eq!(
+            decrypt_mnemonic(&path, "passphrase").unwrap().as_str(),
+            mnemonic
+        );
+        assert!(decrypt_mnemonic(&path, "wrong passphrase").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! Taprootized atomic swaps between Bitcoin and Ethereum.
+//!
+//! The initiator swaps satoshis for ether. It locks the satoshis in a taproot output whose escrow
+//! key is the sum of the swap key `K = k * G` and the counterparty's key, and sends the
+//! counterparty a [`SwapOffer`] with the zero-knowledge proof that `K` and the Poseidon hash of
+//! `k` come from the same secret. The counterparty verifies the offer, deposits the ether to the
+//! `Depositor` contract under the secret hash, and once the initiator withdraws it revealing `k`,
+//! spends the taproot output with the escrow key. If either party goes silent, the other one
+//! refunds after the lock time: the counterparty restores the deposit, and the initiator spends
+//! the output through its revocation script path after the CSV delay.
+//!
+//! [`SwapParticipant`] runs either side of the swap:
+//! - [`SwapParticipant::build_offer`] and [`SwapParticipant::fund_atomic_swap`] build the offer
+//!   and fund it on Bitcoin, or [`SwapParticipant::new_atomic_swap`] does both;
+//! - [`SwapParticipant::reserve_swap_keys`] starts a new swap with its own keys before the
+//!   counterparty shares its public key;
+//! - [`SwapParticipant::verify_offer`] checks the counterparty's offer, and
+//!   [`SwapParticipant::accept_atomic_swap`] verifies it, waits for the funding and deposits;
+//! - [`SwapParticipant::resume_swap`] resumes the swap from the swap store after a restart;
+//! - [`SwapParticipant::listen_to_deposit_events`] and
+//!   [`SwapParticipant::listen_to_withdraw_events`] redeem the ether and the satoshis;
+//! - [`SwapParticipant::refund_ethereum_deposit`] and
+//!   [`SwapParticipant::refund_bitcoin_swap_tx`] refund them.
+//!
+//! The participant is configured with [`config::Config`], see `config.example.toml`.
+
+use std::ops::{Add, Div, Mul};
+
+use num::{BigInt, One, ToPrimitive, Zero};
+
+pub use crate::offer::SwapOffer;
+pub use crate::participant::{ParticipantKeys, SwapParticipant};
+pub use crate::secret::SwapSecret;
+
+pub mod artifacts;
+pub mod config;
+pub mod depositor_contract;
+pub mod gas;
+pub mod hd;
+pub mod keystore;
+pub mod offer;
+pub mod pubsignals;
+pub mod recovery;
+pub mod relayed_depositor_contract;
+pub mod relayer;
+pub mod secret;
+pub mod signer;
+pub mod store;
+pub mod verified_depositor_contract;
+
+mod bitcoin_backend;
+mod events;
+mod mempool;
+mod participant;
+mod poseidon;
+mod web3signer;
+
+/// Splits the 256-bit `input` into four 64-bit limbs, the least significant one first, or returns
+/// [`None`] if it doesn't fit.
+pub fn u256_to_u64array(mut input: BigInt) -> Option<[u64; 4]> {
+    let mut result = [0u64; 4];
+
+    let u64_max = BigInt::from(u64::MAX) + BigInt::one();
+
+    for x in result.iter_mut() {
+        let rem = input.clone() % u64_max.clone();
+        *x = rem.to_u64().expect("mod of u64 can't be gr than u64");
+        input = input.div(u64_max.clone())
+    }
+
+    if input != BigInt::zero() {
+        return None;
+    }
+
+    Some(result)
+}
+
+/// Joins the four 64-bit limbs, the least significant one first, into the 256-bit integer.
+pub fn u64array_to_u256(input: [u64; 4]) -> BigInt {
+    let mut result = BigInt::from(input[3]);
+
+    let u64_max = BigInt::from(u64::MAX) + BigInt::one();
+
+    for i in (0..=2).rev() {
+        result = result.mul(u64_max.clone());
+        result = result.add(BigInt::from(input[i]));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use num::BigInt;
+
+    use crate::{u256_to_u64array, u64array_to_u256};
+
+    #[test]
+    fn test_u256_to_u64array() {
+        do_test_u256_to_u64array(
+            BigInt::from_str(
+                "112874956271937818984300676023995443620017137826812392247603206681821520986618",
+            )
+            .unwrap(),
+            vec![
+                5264901914485981690,
+                2440863701439358041,
+                12221174418977567583,
+                17982017980625340069,
+            ],
+        );
+        do_test_u256_to_u64array(BigInt::from_str("1").unwrap(), vec![1, 0, 0, 0]);
+        do_test_u256_to_u64array(BigInt::from_str("0").unwrap(), vec![0, 0, 0, 0]);
+        do_test_u256_to_u64array(
+            BigInt::from_str("9134136032198266807219851950679215").unwrap(),
+            vec![5858208856384070831, 495162506494374, 0, 0],
+        );
+    }
+
+    fn do_test_u256_to_u64array(expected_u256: BigInt, expected_u64array: Vec<u64>) {
+        assert_eq!(expected_u64array.len(), 4);
+
+        let u64array = u256_to_u64array(expected_u256.clone()).unwrap();
+
+        assert_eq!(u64array[0], expected_u64array[0]);
+        assert_eq!(u64array[1], expected_u64array[1]);
+        assert_eq!(u64array[2], expected_u64array[2]);
+        assert_eq!(u64array[3], expected_u64array[3]);
+
+        let u256 = u64array_to_u256(u64array);
+
+        assert_eq!(u256, expected_u256);
+    }
+}
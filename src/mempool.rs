@@ -0,0 +1,50 @@
+//! Watcher of the pending `withdraw` transactions.
+//!
+//! The swap secret is public as soon as the counterparty's `withdraw` is in the mempool, so the
+//! Bitcoin redeem can start before the transaction is mined, keeping more of the CSV safety
+//! margin. Every pending transaction is fetched to be checked, so the watcher is optional.
+
+use std::time::Duration;
+
+use ethers::providers::{Middleware, Provider, StreamExt, Ws};
+use ethers::types::Address as EthereumAddress;
+
+use crate::recovery::swap_secret_from_calldata;
+use crate::secret::SwapSecret;
+
+/// Delay before resubscribing after the subscription has failed.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Waits for a pending `withdraw` transaction to the contract revealing the secret of the
+/// `secret_hash`.
+pub async fn watch_pending_withdrawal(
+    client: &Provider<Ws>,
+    contract_address: EthereumAddress,
+    secret_hash: [u8; 32],
+) -> SwapSecret {
+    loop {
+        match client.subscribe_pending_txs().await {
+            Ok(mut pending_txs) => {
+                while let Some(tx_hash) = pending_txs.next().await {
+                    // The transaction may have been dropped or replaced in the meantime.
+                    let Ok(Some(tx)) = client.get_transaction(tx_hash).await else {
+                        continue;
+                    };
+
+                    if tx.to != Some(contract_address) {
+                        continue;
+                    }
+
+                    if let Some(swap_secret) = swap_secret_from_calldata(&tx.input, secret_hash) {
+                        return swap_secret;
+                    }
+                }
+
+                println!("| Pending transactions subscription has ended, resubscribing");
+            }
+            Err(err) => println!("| Failed to subscribe to pending transactions: {err}"),
+        }
+
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
@@ -0,0 +1,12 @@
+/// The atomic-swap offer the initiator sends to the counterparty along with the taproot
+/// transaction on Bitcoin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapOffer {
+    /// The snarkjs `proof.json` of the atomic-swap proof.
+    pub proof: String,
+    /// The snarkjs `public.json` of the atomic-swap proof.
+    pub pubsignals: String,
+    /// Hash of the verification key the proof is made for, see
+    /// [`crate::artifacts::verification_key_hash`].
+    pub verification_key_hash: [u8; 32],
+}
@@ -0,0 +1,1642 @@
+//! The swap participant, either the initiator that swaps satoshis for ether or the counterparty.
+//!
+//! The initiator builds the swap offer with [`SwapParticipant::new_atomic_swap`], which funds the
+//! taprootized transaction on Bitcoin, and redeems the counterparty's deposit with
+//! [`SwapParticipant::listen_to_deposit_events`], revealing the secret. The counterparty accepts
+//! the offer with [`SwapParticipant::accept_atomic_swap`], which deposits the ether, and redeems
+//! the satoshis with the revealed secret with [`SwapParticipant::listen_to_withdraw_events`].
+//! Either party refunds its funds after the lock time if the other one has gone.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "prover")]
+use std::collections::HashMap;
+use std::ops::Mul;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bdk::bitcoin::hashes::hex::ToHex;
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::secp256k1::{All, Scalar, Secp256k1};
+use bdk::bitcoin::{secp256k1, Address as BitcoinAddress, Txid as BitcoinTxid};
+use bdk::blockchain::Blockchain;
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::template::DescriptorTemplateOut;
+use bdk::descriptor::{ExtendedDescriptor, IntoWalletDescriptor};
+use bdk::miniscript::descriptor::TapTree;
+use bdk::miniscript::policy::Concrete;
+use bdk::miniscript::Descriptor;
+use bdk::signer::{SignerContext, SignerOrdering, SignerWrapper};
+use bdk::wallet::AddressIndex;
+use bdk::{
+    bitcoin, FeeRate, KeychainKind, SignOptions, SyncOptions, Wallet as BitcoinWallet, Wallet,
+};
+use ethers::contract::parse_log;
+use ethers::prelude::SignerMiddleware;
+use ethers::providers::{Middleware, Provider as EthereumClient, Provider, Ws};
+use ethers::signers::{LocalWallet as EthereumWallet, Signer};
+use ethers::types::U256;
+use ethers::types::{Address as EthereumAddress, Filter, TxHash};
+use ethers::utils::Units::Gwei;
+use eyre::{eyre, Context, Result};
+#[cfg(feature = "prover")]
+use num::BigInt;
+use num::BigUint;
+use rand::rngs::ThreadRng;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "prover")]
+use rapidsnark::groth16_prover;
+use rapidsnark::{FromJson, PublicInputs, SolidityCalldata, Verifier, ZkProof};
+
+use crate::bitcoin_backend::{self, cancellable, estimate_feerate, BitcoinBackend};
+use crate::config::{CircomConfig, Config, SwapParams, WalletKeys, WalletsConfig};
+use crate::depositor_contract::{
+    DepositedFilter, Depositor as DepositorContract, Depositor, WithdrawnFilter,
+};
+use crate::events::EventFollower;
+use crate::gas::GasConfig;
+use crate::hd::SwapKeys;
+use crate::offer::SwapOffer;
+use crate::pubsignals::AtomicSwapPublicSignals;
+use crate::recovery::recover_swap_secret;
+use crate::relayer::{RelayRequest, RelayedWithdraw, RelayerClient, RelayerClientConfig};
+use crate::secret::SwapSecret;
+use crate::signer::{finalize_psbt, BitcoinSigner, EthereumSigner};
+use crate::store::{SwapRole, SwapStore};
+use crate::{artifacts, mempool};
+
+/// Number of the BDK wallet's sync tries to find the taproot atomic-swap transaction on-chain that
+/// has been published by a counterparty.
+const MAX_NUMBER_OF_ATTEMPTS_TO_SYNC: usize = 300;
+
+/// Delay between attempts to sync the BDK wallet to find the taproot atomic-swap transaction.
+const DELAY_BETWEEN_SYNC_ATTEMPT_SEC: u64 = 5;
+
+/// Participant's keys of the current swap, see [`crate::hd`].
+pub struct ParticipantKeys {
+    /// The revocation key of the initiator and the counterparty's part of the escrow key.
+    pub bitcoin: secp256k1::KeyPair,
+    /// The address the counterparty withdraws the swapped satoshis to.
+    pub bitcoin_redeem_address: BitcoinAddress,
+    /// The address the swapped ether is deposited for.
+    pub ethereum_address: EthereumAddress,
+    /// The wallet of [`Self::ethereum_address`] if it's derived from the swap keys, that signs the
+    /// relayed withdraw.
+    pub ethereum_wallet: Option<EthereumWallet>,
+}
+
+impl ParticipantKeys {
+    /// Returns the swap keys with the `signer_address` receiving the deposit if the swap keys
+    /// don't include the Ethereum key.
+    pub fn from_swap_keys(
+        keys: SwapKeys,
+        signer_address: EthereumAddress,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<Self> {
+        let ethereum_wallet = keys
+            .ethereum
+            .map(|key| EthereumWallet::from_bytes(&key.secret_bytes()))
+            .transpose()?;
+        let ethereum_address = ethereum_wallet
+            .as_ref()
+            .map_or(signer_address, |wallet| wallet.address());
+
+        Ok(Self {
+            bitcoin: secp256k1::KeyPair::from_secret_key(secp_ctx, &keys.bitcoin),
+            bitcoin_redeem_address: keys.bitcoin_redeem_address,
+            ethereum_address,
+            ethereum_wallet,
+        })
+    }
+}
+
+/// The swap participant that signs Ethereum transactions with `S`, the signer from the config by
+/// default.
+pub struct SwapParticipant<S = EthereumSigner> {
+    name: String,
+    /// The participant's keys that the keys of every swap come from.
+    wallet_keys: WalletKeys,
+    /// The keys of the current swap, see [`Self::reserve_swap_keys`]. They are [`None`] until
+    /// then if they are derived from the mnemonic.
+    keys: Option<ParticipantKeys>,
+
+    /// The swap store and the derivation index of [`Self::keys`] if they are derived from the
+    /// mnemonic.
+    swap_store: Option<SwapStore>,
+    swap_index: Option<u32>,
+
+    swap_params: SwapParams,
+    gas: GasConfig,
+    /// The relayer the deposit is withdrawn through if set, see [`crate::relayer`].
+    withdraw_relayer: Option<RelayerClientConfig>,
+
+    /// The Ethereum block at the start, that the event listeners start from if they don't have a
+    /// checkpoint in the swap store.
+    ethereum_start_block: u64,
+    /// The Ethereum block the counterparty's deposit of the current swap is sent after, that the
+    /// withdraw revealing the secret is searched for from.
+    deposit_block: Option<u64>,
+    ethereum_confirmations: u64,
+    /// Whether the swap secret is taken from the pending withdraw, see [`crate::mempool`].
+    watch_mempool: bool,
+
+    /// Swap secret that it needed to spend locked money from either Bitcoin or Ethereum
+    /// atomic-swap.
+    ///
+    /// It is [`Some`] only after either [`Self::build_offer`] for swap initiator or after
+    /// counterparty noticed initiator withdrawal transaction.
+    swap_secret: Option<SwapSecret>,
+
+    /// Posidon hash of the swap secret.
+    ///
+    /// It is [`Some`] only after either [`Self::build_offer`] for swap initiator or
+    /// [`Self::accept_atomic_swap`] for swap counterparty.
+    swap_secret_hash: Option<[u8; 32]>,
+
+    /// Counterparty's Bitcoin public key. The initiator's one is the revocation key of the
+    /// Taprootized atomic-swap transaction, and the counterparty's one is a part of its escrow key.
+    ///
+    /// It is [`Some`] only after either [`Self::build_offer`] for swap initiator or
+    /// [`Self::accept_atomic_swap`] for swap counterparty.
+    counterparty_bitcoin_pubkey: Option<secp256k1::PublicKey>,
+
+    /// Public key of the swap secret, that is a part of the escrow key of the Taprootized
+    /// atomic-swap transaction with the counterparty's one.
+    ///
+    /// It is [`Some`] only after either [`Self::build_offer`] for swap initiator or
+    /// [`Self::accept_atomic_swap`] for swap counterparty.
+    swap_pubkey: Option<secp256k1::PublicKey>,
+
+    atomic_swap_contract_address: EthereumAddress,
+    deposit_with_proof: bool,
+    circom: CircomConfig,
+    verifier: Verifier,
+    /// Hash of the verification key that is embedded in the swap offers to make sure both the
+    /// parties use the same circuit.
+    verification_key_hash: [u8; 32],
+    ethereum_client: EthereumClient<Ws>,
+
+    /// The funding wallet with its RPC client, that are used on the blocking thread pool.
+    bitcoin_backend: BitcoinBackend,
+    /// Feerate used if the node fails to estimate one.
+    bitcoin_fallback_feerate: Option<FeeRate>,
+    bitcoin_signer: BitcoinSigner,
+    ethereum_signer: S,
+
+    /// Cancels the swap's long-running waits, e.g. on Ctrl-C.
+    cancellation: CancellationToken,
+}
+
+impl SwapParticipant {
+    /// Initializes the participant with the Ethereum signer from the config.
+    pub async fn from_config(
+        name: String,
+        config: &Config,
+        wallets_config: &WalletsConfig,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<Self> {
+        Self::from_config_with_signer(
+            name,
+            config,
+            wallets_config,
+            secp_ctx,
+            |wallet_keys, chain_id| {
+                wallets_config
+                    .ethereum_signer
+                    .signer(wallet_keys.ethereum_funding_key(secp_ctx)?, chain_id)
+            },
+        )
+        .await
+    }
+}
+
+impl<S: Signer + Clone + 'static> SwapParticipant<S> {
+    /// Initializes the participant with the Ethereum signer that `make_signer` returns for the
+    /// participant's keys and the chain ID.
+    pub async fn from_config_with_signer(
+        name: String,
+        config: &Config,
+        wallets_config: &WalletsConfig,
+        secp_ctx: &Secp256k1<All>,
+        make_signer: impl FnOnce(&WalletKeys, u64) -> Result<S>,
+    ) -> Result<Self> {
+        wallets_config.validate()?;
+
+        let network = config.bitcoin_rpc.network;
+        let wallet_keys = wallets_config
+            .keys(&name, network)
+            .wrap_err("failed to load private keys")?;
+
+        let swap_store = wallets_config.swap_store(&wallet_keys)?;
+
+        let ethereum_client = config
+            .ethereum_client()
+            .await
+            .wrap_err("failed to initialize Ethereum RPC client")?;
+
+        let chain_id = ethereum_client.get_chainid().await?;
+        let ethereum_start_block = ethereum_client.get_block_number().await?.as_u64();
+
+        let ethereum_signer = make_signer(&wallet_keys, chain_id.as_u64())
+            .wrap_err("failed to initialize Ethereum signer")?;
+
+        // The single keys are reused by every swap, and the derived ones are reserved per swap.
+        let keys = match wallet_keys {
+            WalletKeys::Hd(_) => None,
+            WalletKeys::Single { .. } => Some(ParticipantKeys::from_swap_keys(
+                wallet_keys.swap_keys(secp_ctx, network, None)?,
+                ethereum_signer.address(),
+                secp_ctx,
+            )?),
+        };
+
+        let (descriptor, change_descriptor) =
+            wallets_config.bitcoin_wallet_descriptors(&wallet_keys, network);
+        // The initial sync blocks, so it runs on the blocking pool with the owned config.
+        let bitcoin_rpc = config.bitcoin_rpc.clone();
+        let blocking_secp_ctx = secp_ctx.clone();
+        let (bitcoin_wallet, bitcoin_client) = bitcoin_backend::spawn_blocking(move || {
+            bitcoin_rpc.bitcoin_wallet(
+                &blocking_secp_ctx,
+                &descriptor,
+                change_descriptor.as_deref(),
+            )
+        })
+        .await
+        .wrap_err("failed to initialize Bitcoin wallet with its RPC client")?;
+
+        let verifier = config
+            .circom
+            .verifier()
+            .wrap_err("failed to initialize atomic-swap proof verifier")?;
+
+        artifacts::check_artifacts(&config.circom, verifier.verification_key())
+            .wrap_err("failed to check Circom artifacts")?;
+        let verification_key_hash = artifacts::verification_key_hash(verifier.verification_key())?;
+
+        println!("Initialized new participant with wallets: ");
+        println!(
+            "Bitcoin funding address: {}",
+            bitcoin_wallet.get_address(AddressIndex::Peek(0))?.address
+        );
+        println!("Ethereum funding address: {}", ethereum_signer.address());
+
+        Ok(Self {
+            name,
+            wallet_keys,
+            keys,
+            swap_store,
+            swap_index: None,
+            counterparty_bitcoin_pubkey: None,
+            swap_pubkey: None,
+            swap_secret: None,
+            swap_secret_hash: None,
+            swap_params: config.swap_params.clone(),
+            gas: config.gas.clone(),
+            withdraw_relayer: wallets_config.withdraw_relayer.clone(),
+            ethereum_start_block,
+            deposit_block: None,
+            ethereum_confirmations: config.ethereum_confirmations,
+            watch_mempool: config.watch_mempool,
+            atomic_swap_contract_address: config.atomic_swap_contract_address,
+            deposit_with_proof: config.deposit_with_proof,
+            circom: config.circom.clone(),
+            verifier,
+            verification_key_hash,
+            ethereum_client,
+            bitcoin_backend: BitcoinBackend::new(bitcoin_wallet, bitcoin_client),
+            bitcoin_fallback_feerate: config
+                .bitcoin_rpc
+                .fallback_feerate_sat_per_vb
+                .map(FeeRate::from_sat_per_vb),
+            bitcoin_signer: wallets_config.bitcoin_signer.clone(),
+            ethereum_signer,
+            cancellation: CancellationToken::new(),
+        })
+    }
+
+    /// Returns the Bitcoin public key of the current swap.
+    pub fn bitcoin_public_key(&self) -> Result<secp256k1::PublicKey> {
+        Ok(self.keys()?.bitcoin.public_key())
+    }
+
+    /// Returns the address the ether of the current swap is deposited for.
+    pub fn ethereum_address(&self) -> Result<EthereumAddress> {
+        Ok(self.keys()?.ethereum_address)
+    }
+
+    fn keys(&self) -> Result<&ParticipantKeys> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| eyre!("swap keys aren't reserved yet"))
+    }
+
+    /// Starts a new swap with its own keys: reserves the next derivation index in the swap store
+    /// and derives the swap keys from it, so the keys of two swaps are never the same. The single
+    /// keys from the config are kept.
+    ///
+    /// [`Self::build_offer`] calls it for the initiator, and the counterparty calls it before
+    /// sharing its [`Self::bitcoin_public_key`].
+    pub fn reserve_swap_keys(&mut self, secp_ctx: &Secp256k1<All>) -> Result<()> {
+        self.swap_secret = None;
+        self.swap_secret_hash = None;
+        self.counterparty_bitcoin_pubkey = None;
+        self.swap_pubkey = None;
+        self.deposit_block = None;
+
+        let WalletKeys::Hd(_) = self.wallet_keys else {
+            return Ok(());
+        };
+
+        let swap_index = self
+            .swap_store
+            .as_mut()
+            .map(|swap_store| swap_store.next_swap())
+            .transpose()?;
+
+        self.use_swap_keys(swap_index, secp_ctx)
+    }
+
+    /// Resumes the swap on the `role` side that hasn't completed before the restart: takes its
+    /// keys, secret hash, the public keys and the deposit's block from the swap store. Then the
+    /// counterparty's [`Self::listen_to_withdraw_events`] continues from its persisted checkpoint,
+    /// or the swap is refunded with [`Self::refund_ethereum_deposit`] or
+    /// [`Self::refund_bitcoin_swap_tx`]. The counterparty's swap whose deposit hasn't been sent
+    /// is dropped. Returns whether there is such a swap.
+    pub fn resume_swap(&mut self, role: SwapRole, secp_ctx: &Secp256k1<All>) -> Result<bool> {
+        let swap = loop {
+            let Some(swap_store) = self.swap_store.as_mut() else {
+                return Ok(false);
+            };
+            let Some(swap) = swap_store.in_flight_swap(role).cloned() else {
+                return Ok(false);
+            };
+
+            if role == SwapRole::Initiator || swap.deposit_block.is_some() {
+                break swap;
+            }
+
+            println!(
+                "| Swap {} is dropped as its deposit hasn't been sent",
+                swap.index
+            );
+            swap_store
+                .set_completed(swap.index)
+                .wrap_err("failed to record swap in the store")?;
+        };
+
+        let (Some(secret_hash), Some(counterparty_bitcoin_pubkey), Some(swap_pubkey)) = (
+            &swap.secret_hash,
+            &swap.counterparty_bitcoin_pubkey,
+            &swap.swap_pubkey,
+        ) else {
+            return Err(eyre!("swap {} is recorded without its keys", swap.index));
+        };
+        let swap_secret_hash: [u8; 32] = hex::decode(secret_hash)
+            .ok()
+            .and_then(|secret_hash| secret_hash.try_into().ok())
+            .ok_or_else(|| eyre!("invalid secret hash of swap {}", swap.index))?;
+        let counterparty_bitcoin_pubkey =
+            secp256k1::PublicKey::from_str(counterparty_bitcoin_pubkey)
+                .wrap_err_with(|| format!("invalid counterparty key of swap {}", swap.index))?;
+        let swap_pubkey = secp256k1::PublicKey::from_str(swap_pubkey)
+            .wrap_err_with(|| format!("invalid swap key of swap {}", swap.index))?;
+
+        println!(
+            "\n= {} resumes atomic-swap {}",
+            self.name,
+            hex::encode(swap_secret_hash)
+        );
+        self.use_swap_keys(Some(swap.index), secp_ctx)?;
+        self.swap_secret = None;
+        self.swap_secret_hash = Some(swap_secret_hash);
+        self.counterparty_bitcoin_pubkey = Some(counterparty_bitcoin_pubkey);
+        self.swap_pubkey = Some(swap_pubkey);
+        self.deposit_block = swap.deposit_block;
+
+        Ok(true)
+    }
+
+    /// Derives the keys of the swap with the derivation `swap_index`.
+    fn use_swap_keys(&mut self, swap_index: Option<u32>, secp_ctx: &Secp256k1<All>) -> Result<()> {
+        let keys = ParticipantKeys::from_swap_keys(
+            self.wallet_keys
+                .swap_keys(secp_ctx, self.bitcoin_backend.network(), swap_index)?,
+            self.ethereum_signer.address(),
+            secp_ctx,
+        )?;
+
+        if let Some(swap_index) = swap_index {
+            println!("| Swap keys derivation index: {swap_index}");
+        }
+        self.swap_index = swap_index;
+        self.keys = Some(keys);
+
+        Ok(())
+    }
+
+    /// Returns the token that cancels the participant's swap.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Records the started or accepted current swap in the swap store.
+    fn record_swap_started(
+        &mut self,
+        role: SwapRole,
+        swap_secret_hash: [u8; 32],
+        counterparty_bitcoin_pubkey: secp256k1::PublicKey,
+        swap_pubkey: secp256k1::PublicKey,
+    ) -> Result<()> {
+        let (Some(swap_store), Some(swap_index)) = (&mut self.swap_store, self.swap_index) else {
+            return Ok(());
+        };
+
+        swap_store
+            .set_started(
+                swap_index,
+                role,
+                swap_secret_hash,
+                counterparty_bitcoin_pubkey.serialize(),
+                swap_pubkey.serialize(),
+            )
+            .wrap_err("failed to record swap in the store")
+    }
+
+    /// Records the block the deposit of the current swap is sent after in the swap store.
+    fn record_swap_deposit_block(&mut self, deposit_block: u64) -> Result<()> {
+        self.deposit_block = Some(deposit_block);
+
+        let (Some(swap_store), Some(swap_index)) = (&mut self.swap_store, self.swap_index) else {
+            return Ok(());
+        };
+
+        swap_store
+            .set_deposit_block(swap_index, deposit_block)
+            .wrap_err("failed to record swap in the store")
+    }
+
+    /// Marks the current swap completed in the swap store.
+    fn record_swap_completed(&mut self) -> Result<()> {
+        let (Some(swap_store), Some(swap_index)) = (&mut self.swap_store, self.swap_index) else {
+            return Ok(());
+        };
+
+        swap_store
+            .set_completed(swap_index)
+            .wrap_err("failed to record swap in the store")
+    }
+
+    /// Starts the swap as the initiator: builds the offer for the counterparty with
+    /// [`Self::build_offer`] and funds the taprootized atomic-swap transaction on Bitcoin with
+    /// [`Self::fund_atomic_swap`].
+    pub async fn new_atomic_swap(
+        &mut self,
+        sats_to_swap: u64,
+        counterparty_bitcoin_pubkey: secp256k1::PublicKey,
+        rng: &mut ThreadRng,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<SwapOffer> {
+        println!("\n= {} starts atomic-swap", self.name);
+
+        let offer = self.build_offer(counterparty_bitcoin_pubkey, rng, secp_ctx)?;
+
+        let tx_id = self
+            .fund_atomic_swap(sats_to_swap, secp_ctx)
+            .await
+            .wrap_err("failed to send swap tx to Bitcoin")?;
+        println!(
+            "| Taprootized atomic-swap transaction has been sent to Bitcoin: {}",
+            tx_id
+        );
+
+        Ok(offer)
+    }
+
+    /// Generates the swap secret and the offer with the proof that the swap public key and the
+    /// secret hash are of the same secret. The offer starts a new swap with its own keys, see
+    /// [`Self::reserve_swap_keys`].
+    pub fn build_offer(
+        &mut self,
+        counterparty_bitcoin_pubkey: secp256k1::PublicKey,
+        rng: &mut ThreadRng,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<SwapOffer> {
+        self.reserve_swap_keys(secp_ctx)?;
+
+        let swap_secret = SwapSecret::random(rng);
+        self.swap_secret = Some(swap_secret);
+        self.counterparty_bitcoin_pubkey = Some(counterparty_bitcoin_pubkey);
+
+        println!(
+            "| Swap k secret: {}",
+            swap_secret.secret_key().display_secret()
+        );
+
+        println!("| Calculating zero-knowledge proof...");
+        let (proof, pubsignals) = self
+            .generate_swap_proof(&swap_secret)
+            .wrap_err("failed to generate atomic-swap proof")?;
+
+        let AtomicSwapPublicSignals {
+            pubkey: swap_pubkey,
+            secret_hash: swap_secret_hash,
+        } = AtomicSwapPublicSignals::from_json(&pubsignals)?;
+
+        if swap_secret.hash() != swap_secret_hash {
+            return Err(eyre!(
+                "secret hash from the proof doesn't match the Poseidon hash of the secret"
+            ));
+        }
+        if swap_secret.secret_key().public_key(secp_ctx) != swap_pubkey {
+            return Err(eyre!(
+                "public key from the proof doesn't match the public key of the secret"
+            ));
+        }
+        self.swap_secret_hash = Some(swap_secret_hash);
+        self.swap_pubkey = Some(swap_pubkey);
+        self.record_swap_started(
+            SwapRole::Initiator,
+            swap_secret_hash,
+            counterparty_bitcoin_pubkey,
+            swap_pubkey,
+        )?;
+
+        println!("| Swap k public: {}", swap_pubkey);
+        println!("| Swap secret's hash: {}", hex::encode(swap_secret_hash));
+
+        Ok(SwapOffer {
+            proof,
+            pubsignals,
+            verification_key_hash: self.verification_key_hash,
+        })
+    }
+
+    /// Sends `sats_to_swap` to the taprootized atomic-swap output of the offer built with
+    /// [`Self::build_offer`].
+    pub async fn fund_atomic_swap(
+        &self,
+        sats_to_swap: u64,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<BitcoinTxid> {
+        let escrow_pubkey = self.escrow_pubkey()?;
+
+        self.send_atomic_swap_tx_to_bitcoin(sats_to_swap, escrow_pubkey, secp_ctx)
+            .await
+    }
+
+    /// Verifies that the offer is made for the same circuit and its proof is valid, and returns
+    /// the offer's public signals.
+    pub fn verify_offer(&self, offer: &SwapOffer) -> Result<AtomicSwapPublicSignals> {
+        if offer.verification_key_hash != self.verification_key_hash {
+            return Err(eyre!(
+                "atomic-swap proof is made for another circuit: verification key hash {}, \
+                 expected {}",
+                hex::encode(offer.verification_key_hash),
+                hex::encode(self.verification_key_hash)
+            ));
+        }
+
+        println!("| Verifying zero-knowledge proof...");
+        if !self.verify_swap_proof(offer.proof.clone(), offer.pubsignals.clone())? {
+            return Err(eyre!("invalid atomic-swap proof"));
+        }
+
+        AtomicSwapPublicSignals::from_json(&offer.pubsignals)
+    }
+
+    /// Accepts the swap as the counterparty: verifies the offer, waits for the initiator's
+    /// taprootized transaction on Bitcoin and deposits the ether for the initiator.
+    /// The swap keys are the ones reserved with [`Self::reserve_swap_keys`] before sharing the
+    /// public key with the initiator.
+    pub async fn accept_atomic_swap(
+        &mut self,
+        offer: SwapOffer,
+        counterparty_bitcoin_pubkey: secp256k1::PublicKey,
+        counterparty_ethereum_address: EthereumAddress,
+    ) -> Result<()> {
+        println!("\n= {} accepts atomic-swap", self.name);
+
+        self.counterparty_bitcoin_pubkey = Some(counterparty_bitcoin_pubkey);
+
+        let AtomicSwapPublicSignals {
+            pubkey: swap_pubkey,
+            secret_hash: swap_secret_hash,
+        } = self.verify_offer(&offer)?;
+        self.swap_secret_hash = Some(swap_secret_hash);
+        self.swap_pubkey = Some(swap_pubkey);
+        self.record_swap_started(
+            SwapRole::Counterparty,
+            swap_secret_hash,
+            counterparty_bitcoin_pubkey,
+            swap_pubkey,
+        )?;
+
+        let swap_transaction_found = self
+            .check_atomic_swap_tx_appeared_on_bitcoin(swap_pubkey, counterparty_bitcoin_pubkey)
+            .await
+            .wrap_err("failed to check if atomic-swap transaction appeared in Bitcoin")?;
+
+        if !swap_transaction_found {
+            return Err(eyre!(
+                "taproot atomic-swap transaction hasn't appeared; swap_pubkey: {swap_pubkey}"
+            ));
+        }
+
+        let swap_proof_calldata = if self.deposit_with_proof {
+            Some(
+                swap_proof_calldata(&offer.proof, &offer.pubsignals)
+                    .wrap_err("failed to encode atomic-swap proof as Solidity calldata")?,
+            )
+        } else {
+            None
+        };
+
+        // The withdraw revealing the secret can't precede the deposit, so it's searched for since
+        // the latest block before it.
+        let deposit_block = self
+            .ethereum_client
+            .get_block_number()
+            .await
+            .wrap_err("failed to get latest Ethereum block")?
+            .as_u64();
+        self.record_swap_deposit_block(deposit_block)?;
+
+        let tx_id = self
+            .send_atomic_swap_tx_to_ethereum(
+                swap_secret_hash,
+                counterparty_ethereum_address,
+                swap_proof_calldata,
+            )
+            .await?;
+
+        println!(
+            "| Atomic-swap transaction has been sent to Ethereum: {}",
+            tx_id.to_hex()
+        );
+
+        Ok(())
+    }
+
+    /// Refunds the counterparty's deposit with `restore` once its lock time has passed and the
+    /// initiator hasn't withdrawn it.
+    pub async fn refund_ethereum_deposit(&mut self) -> Result<TxHash> {
+        let Some(swap_secret_hash) = self.swap_secret_hash else {
+            return Err(eyre!("swap secret hash is absent"));
+        };
+
+        let contract = self.deposit_contract();
+        let contract_call = contract.restore(swap_secret_hash);
+
+        // The contract reverts if the lock time hasn't passed, so don't pay for it.
+        contract_call
+            .call()
+            .await
+            .wrap_err("deposit can't be restored")?;
+
+        let tx_id = self
+            .gas
+            .send_transaction(contract.client_ref(), contract_call.tx)
+            .await?;
+        println!(
+            "\n= {} has restored the deposit on Ethereum in: {}",
+            self.name,
+            tx_id.to_hex()
+        );
+        self.record_swap_completed()?;
+
+        Ok(tx_id)
+    }
+
+    /// Refunds the initiator's satoshis through the revocation script path once the CSV delay
+    /// has passed and the counterparty hasn't redeemed them.
+    pub async fn refund_bitcoin_swap_tx(&mut self) -> Result<BitcoinTxid> {
+        let escrow_pubkey = bitcoin::PublicKey::new(self.escrow_pubkey()?);
+        let network = self.bitcoin_backend.network();
+        let revocation_keypair = self.keys()?.bitcoin;
+        let revocation_pubkey = bitcoin::PublicKey::new(revocation_keypair.public_key());
+        let revocation_privkey = bitcoin::PrivateKey::new(revocation_keypair.secret_key(), network);
+        let bitcoin_csv_delay = self.swap_params.bitcoin_csv_delay;
+        let recepient_address = self.keys()?.bitcoin_redeem_address.clone();
+        let fallback_feerate = self.bitcoin_fallback_feerate;
+        let bitcoin_signer = self.bitcoin_signer.clone();
+
+        let (wallet, mut psbt) = self
+            .bitcoin_backend
+            .with_client(move |bitcoin_client| {
+                let mut wallet = Wallet::new(
+                    refund_descriptor(escrow_pubkey, revocation_pubkey, bitcoin_csv_delay)?,
+                    None,
+                    network,
+                    MemoryDatabase::new(),
+                )?;
+                if let BitcoinSigner::InProcess = bitcoin_signer {
+                    add_revocation_signer(&mut wallet, revocation_privkey);
+                }
+
+                wallet
+                    .sync(bitcoin_client, SyncOptions::default())
+                    .wrap_err("failed to sync a BDK wallet")?;
+
+                let feerate = estimate_feerate(bitcoin_client, fallback_feerate)?;
+
+                let mut psbt = build_refund_psbt(&wallet, &recepient_address, feerate)?;
+                bitcoin_signer.sign_in_process(&wallet, &mut psbt)?;
+
+                Ok((wallet, psbt))
+            })
+            .await?;
+
+        self.bitcoin_signer
+            .sign_externally(&mut psbt, &self.cancellation)
+            .await?;
+
+        let tx_id = self
+            .bitcoin_backend
+            .with_client(move |bitcoin_client| {
+                finalize_psbt(&wallet, &mut psbt)?;
+
+                let txid = psbt.unsigned_tx.txid();
+
+                bitcoin_client
+                    .broadcast(&psbt.extract_tx())
+                    .wrap_err("failed to broadcast refund, the CSV delay may not have passed")?;
+
+                Ok(txid)
+            })
+            .await?;
+        println!(
+            "\n= {} has refunded locked money on Bitcoin in: {}",
+            self.name, tx_id
+        );
+        self.record_swap_completed()?;
+
+        Ok(tx_id)
+    }
+
+    /// Returns the escrow key of the initiator's taprootized atomic-swap output, the sum of the
+    /// swap key and the counterparty's key.
+    fn escrow_pubkey(&self) -> Result<secp256k1::PublicKey> {
+        let (Some(swap_pubkey), Some(counterparty_bitcoin_pubkey)) =
+            (self.swap_pubkey, self.counterparty_bitcoin_pubkey)
+        else {
+            return Err(eyre!("swap offer hasn't been built"));
+        };
+
+        Ok(swap_pubkey
+            .combine(&counterparty_bitcoin_pubkey)
+            .expect("It's impossible to fail for 2 different public keys"))
+    }
+
+    /// Returns the follower of the events matching the `filter` that persists its checkpoint
+    /// in the swap store under the `listener` name of the swap.
+    fn event_follower(
+        &mut self,
+        filter: Filter,
+        listener: &str,
+        swap_secret_hash: [u8; 32],
+    ) -> EventFollower<'_> {
+        let listener = format!("{listener}:{}", hex::encode(swap_secret_hash));
+        let from_block = self
+            .swap_store
+            .as_ref()
+            .and_then(|swap_store| swap_store.ethereum_checkpoint(&listener))
+            .unwrap_or(self.ethereum_start_block);
+
+        let swap_store = &mut self.swap_store;
+        EventFollower::new(
+            &self.ethereum_client,
+            filter,
+            self.ethereum_confirmations,
+            from_block,
+            Box::new(move |block| match swap_store.as_mut() {
+                Some(swap_store) => swap_store.set_ethereum_checkpoint(&listener, block),
+                None => Ok(()),
+            }),
+        )
+    }
+
+    pub async fn listen_to_deposit_events(mut self) -> Result<()> {
+        let Some(swap_secret_hash) = self.swap_secret_hash else {
+            return Err(eyre!("swap secret hash is absent"));
+        };
+
+        let Some(swap_secret) = self.swap_secret else {
+            return Err(eyre!("swap secret is absent"));
+        };
+
+        let cancellation = self.cancellation.clone();
+        let filter = self.deposit_contract().deposited_filter().filter;
+        let mut deposits = self.event_follower(filter, "deposited", swap_secret_hash);
+        // TODO: Here we also MUST wait for the CSV of our atomic-swap transaction in Bitcoin.
+        // If we can spend the Bitcoin transaction with revocation (internal) key - we must do it.
+        loop {
+            let log = cancellable(&cancellation, deposits.next()).await?;
+            let deposit: DepositedFilter =
+                parse_log(log).wrap_err("failed to decode deposit event")?;
+
+            if deposit.secret_hash == swap_secret_hash {
+                break;
+            }
+        }
+        drop(deposits);
+
+        let tx_id = self.withdraw_money_from_swap_contract(swap_secret).await?;
+        println!(
+            "\n= {} has spent locked money on Ethereum in: {}",
+            self.name,
+            tx_id.to_hex()
+        );
+        self.record_swap_completed()?;
+
+        Ok(())
+    }
+
+    /// Waits for the `Withdrawn` event of the swap and returns the revealed secret.
+    async fn wait_for_withdrawal(&mut self, swap_secret_hash: [u8; 32]) -> Result<SwapSecret> {
+        let cancellation = self.cancellation.clone();
+        let filter = self.deposit_contract().withdrawn_filter().filter;
+        let mut withdrawals = self.event_follower(filter, "withdrawn", swap_secret_hash);
+
+        // TODO: Here we also MUST wait for the CSV of our atomic-swap transaction in Bitcoin.
+        // If we can spend the Bitcoin transaction with revocation (internal) key - we must do it.
+        loop {
+            let log = cancellable(&cancellation, withdrawals.next()).await?;
+            let withdrawal: WithdrawnFilter =
+                parse_log(log).wrap_err("failed to decode withdraw event")?;
+
+            if withdrawal.secret_hash == swap_secret_hash {
+                let secret = SwapSecret::from_u256(withdrawal.secret)
+                    .wrap_err("revealed secret is not a valid swap secret")?;
+
+                if secret.hash() != swap_secret_hash {
+                    return Err(eyre!("revealed secret doesn't match the swap secret hash"));
+                }
+
+                return Ok(secret);
+            }
+        }
+    }
+
+    pub async fn listen_to_withdraw_events(mut self) -> Result<()> {
+        let Some(swap_secret_hash) = self.swap_secret_hash else {
+            return Err(eyre!("swap secret hash is absent"));
+        };
+
+        let Some(counterparty_bitcoin_pubkey) = self.counterparty_bitcoin_pubkey else {
+            return Err(eyre!("counterparty bitcoin pubkey is absent"));
+        };
+
+        // The counterparty may have withdrawn already, e.g. while we were offline, so the search
+        // starts from the deposit rather than from our start.
+        let contract = self.deposit_contract();
+        let from_block = self.deposit_block.unwrap_or(self.ethereum_start_block);
+        let recovered_secret = recover_swap_secret(&contract, swap_secret_hash, from_block).await?;
+
+        let (swap_secret, is_withdrawal_pending) = match recovered_secret {
+            Some(swap_secret) => {
+                println!("| Swap secret has been recovered from the withdrawn deposit");
+                (swap_secret, false)
+            }
+            None if self.watch_mempool => {
+                let ethereum_client = self.ethereum_client.clone();
+                let pending_withdrawal = mempool::watch_pending_withdrawal(
+                    &ethereum_client,
+                    self.atomic_swap_contract_address,
+                    swap_secret_hash,
+                );
+
+                tokio::select! {
+                    swap_secret = pending_withdrawal => {
+                        println!("| Swap secret has been revealed by the pending withdraw");
+                        (swap_secret, true)
+                    }
+                    swap_secret = self.wait_for_withdrawal(swap_secret_hash) => {
+                        (swap_secret?, false)
+                    }
+                }
+            }
+            None => (self.wait_for_withdrawal(swap_secret_hash).await?, false),
+        };
+
+        let tx_id = self
+            .withdraw_money_from_taprootized_swap_tx(
+                counterparty_bitcoin_pubkey,
+                swap_secret.secret_key(),
+            )
+            .await?;
+        println!(
+            "\n= {} has spent locked money on Bitcoin in: {}",
+            self.name,
+            tx_id.to_hex()
+        );
+
+        // The swap is complete only once the withdraw is mined.
+        if is_withdrawal_pending {
+            let mined_secret = self.wait_for_withdrawal(swap_secret_hash).await?;
+            if mined_secret != swap_secret {
+                return Err(eyre!("mined withdraw has revealed another secret"));
+            }
+            println!("| Withdraw revealing the swap secret has been mined");
+        }
+        self.record_swap_completed()?;
+
+        Ok(())
+    }
+}
+
+impl<S: Signer + Clone + 'static> SwapParticipant<S> {
+    #[cfg(feature = "prover")]
+    fn generate_swap_proof(&self, swap_secret: &SwapSecret) -> Result<(String, String)> {
+        let swap_secret_u64array = swap_secret
+            .to_limbs()
+            .iter()
+            .map(|val| BigInt::from(*val))
+            .collect();
+
+        let mut prover_inputs = HashMap::new();
+        prover_inputs.insert("secret".to_string(), swap_secret_u64array);
+
+        let mut witness_calculator = self
+            .circom
+            .witness_calculator()
+            .wrap_err("failed to load witness calculator")?;
+
+        // This process takes most of the time of the proof generation because of WASM. The C
+        // binding can be used to speed it up.
+        let witness = witness_calculator
+            .calculate_witness(prover_inputs, true)
+            .wrap_err("failed to calculate witness")?;
+
+        let proving_key = self
+            .circom
+            .proving_key()
+            .wrap_err("failed to load proving key")?;
+
+        let proof = groth16_prover(&proving_key, &witness.to_wtns_bytes())
+            .wrap_err("failed to generate groth16 proof")?;
+
+        Ok(proof)
+    }
+
+    /// Only the swap initiator generates proofs, so the responder-only build fails here.
+    #[cfg(not(feature = "prover"))]
+    fn generate_swap_proof(&self, _swap_secret: &SwapSecret) -> Result<(String, String)> {
+        Err(eyre!(
+            "proof generation is unavailable: built without the `prover` feature"
+        ))
+    }
+
+    fn verify_swap_proof(&self, proof: String, pubsignals_json: String) -> Result<bool> {
+        let is_proof_valid = self
+            .verifier
+            .verify_json(proof.as_bytes(), pubsignals_json.as_bytes())
+            .wrap_err("failed to verify proof")?;
+
+        Ok(is_proof_valid)
+    }
+
+    fn signer_client(&self) -> Arc<SignerMiddleware<Provider<Ws>, S>> {
+        Arc::new(SignerMiddleware::new(
+            self.ethereum_client.clone(),
+            self.ethereum_signer.clone(),
+        ))
+    }
+
+    fn deposit_contract(&self) -> Depositor<SignerMiddleware<Provider<Ws>, S>> {
+        DepositorContract::new(self.atomic_swap_contract_address, self.signer_client())
+    }
+
+    /// Withdraws the deposit, resubmitting the withdraw with bumped fees if it's pending too close
+    /// to the deposit's lock time. With the withdraw relayer, it's sent through the relayer instead.
+    async fn withdraw_money_from_swap_contract(&self, swap_secret: SwapSecret) -> Result<TxHash> {
+        let contract = self.deposit_contract();
+
+        let (_, _, _, lock_time, _) = contract.deposits(swap_secret.hash()).call().await?;
+
+        if let Some(relayer_config) = &self.withdraw_relayer {
+            return self
+                .withdraw_through_relayer(relayer_config, swap_secret, lock_time)
+                .await;
+        }
+
+        let contract_call = contract.withdraw(swap_secret.to_u256());
+        self.gas
+            .send_transaction_before(contract.client_ref(), contract_call.tx, lock_time)
+            .await
+    }
+
+    /// Signs the relayed withdraw valid until the `deadline` and submits it to the relayer, that
+    /// takes its fee out of the deposit.
+    async fn withdraw_through_relayer(
+        &self,
+        relayer_config: &RelayerClientConfig,
+        swap_secret: SwapSecret,
+        deadline: U256,
+    ) -> Result<TxHash> {
+        let relayer = RelayerClient::new(&relayer_config.url)?;
+        let relayer_info = relayer.info().await?;
+
+        let max_fee = U256::from(relayer_config.max_fee_gwei) * U256::exp10(Gwei.as_num() as usize);
+        if relayer_info.fee > max_fee {
+            return Err(eyre!(
+                "relayer's fee of {} wei exceeds the maximum of {max_fee} wei",
+                relayer_info.fee
+            ));
+        }
+
+        let withdraw = RelayedWithdraw {
+            secret: swap_secret.to_u256(),
+            relayer: relayer_info.address,
+            fee: relayer_info.fee,
+            deadline,
+            chain_id: self.ethereum_client.get_chainid().await?.as_u64(),
+            contract: self.atomic_swap_contract_address,
+        };
+
+        // The deposit is made for the swap key's address if there is one, not the signer's.
+        let signature = match &self.keys()?.ethereum_wallet {
+            Some(wallet) => wallet.sign_typed_data(&withdraw).await?,
+            None => self
+                .ethereum_signer
+                .sign_typed_data(&withdraw)
+                .await
+                .map_err(|err| eyre!("failed to sign relayed withdraw: {err}"))?,
+        };
+
+        let tx_hash = relayer
+            .withdraw(RelayRequest {
+                secret: withdraw.secret,
+                fee: withdraw.fee,
+                deadline,
+                signature: signature.to_vec().into(),
+            })
+            .await?;
+        println!(
+            "| Withdraw is relayed by {:?} for {} wei",
+            relayer_info.address, relayer_info.fee
+        );
+
+        Ok(tx_hash)
+    }
+
+    async fn withdraw_money_from_taprootized_swap_tx(
+        &self,
+        counterparty_bitcoin_pubkey: secp256k1::PublicKey,
+        swap_secret: secp256k1::SecretKey,
+    ) -> Result<BitcoinTxid> {
+        let network = self.bitcoin_backend.network();
+        let escrow_privkey = bitcoin::PrivateKey::new(
+            swap_secret
+                .add_tweak(&Scalar::from_be_bytes(
+                    self.keys()?.bitcoin.secret_key().secret_bytes(),
+                )?)
+                .expect("It's impossible to fail for 2 different public keys"),
+            network,
+        );
+        let revocation_pubkey = bitcoin::PublicKey::new(counterparty_bitcoin_pubkey);
+        let bitcoin_csv_delay = self.swap_params.bitcoin_csv_delay;
+        let recepient_address = self.keys()?.bitcoin_redeem_address.clone();
+        let fallback_feerate = self.bitcoin_fallback_feerate;
+
+        self.bitcoin_backend
+            .with_client(move |bitcoin_client| {
+                let taproot_descriptor = bdk::descriptor!(tr(
+                    escrow_privkey,
+                    and_v(v:pk(revocation_pubkey), older(bitcoin_csv_delay))
+                ))?;
+
+                let wallet = Wallet::new(taproot_descriptor, None, network, MemoryDatabase::new())?;
+
+                wallet
+                    .sync(bitcoin_client, SyncOptions::default())
+                    .wrap_err("failed to sync a BDK wallet")?;
+
+                let wallet_policy = wallet.policies(KeychainKind::External)?.unwrap();
+                let mut path = BTreeMap::new();
+                // We need to use the first leaf of the script path spend, hence the second policy
+                // If you're not sure what's happening here, no worries, this is bit tricky :)
+                // You can learn more here: https://docs.rs/bdk/latest/bdk/wallet/tx_builder/struct.TxBuilder.html#method.policy_path
+                path.insert(wallet_policy.id, vec![0]);
+
+                let (mut psbt, _details) = {
+                    let mut builder = wallet.build_tx();
+
+                    let feerate = estimate_feerate(bitcoin_client, fallback_feerate)?;
+
+                    builder
+                        .fee_rate(feerate)
+                        .drain_wallet()
+                        .drain_to(recepient_address.script_pubkey())
+                        .policy_path(path, KeychainKind::External);
+
+                    builder.finish()?
+                };
+
+                let is_finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+
+                if !is_finalized {
+                    return Err(eyre!("failed to sign and finalize a transaction"));
+                }
+
+                let txid = psbt.unsigned_tx.txid();
+
+                bitcoin_client.broadcast(&psbt.extract_tx())?;
+
+                Ok(txid)
+            })
+            .await
+    }
+
+    /// Deposits the swapped amount to the `Depositor` contract. If `swap_proof_calldata` is
+    /// provided, the deposit is made with `depositWithProof`, so the contract checks that the
+    /// secret hash belongs to the swap public key.
+    async fn send_atomic_swap_tx_to_ethereum(
+        &self,
+        swap_secret_hash: [u8; 32],
+        counterparty_ethereum_address: EthereumAddress,
+        swap_proof_calldata: Option<SolidityCalldata>,
+    ) -> Result<TxHash> {
+        let wei_to_send = U256::from(self.swap_params.gwei_to_swap).mul(10u32.pow(Gwei.as_num()));
+        let lock_time = U256::from(self.swap_params.ethereum_timelock_secs);
+
+        let Some(calldata) = swap_proof_calldata else {
+            let contract = self.deposit_contract();
+
+            let mut contract_call =
+                contract.deposit(counterparty_ethereum_address, swap_secret_hash, lock_time);
+            contract_call.tx.set_value(wei_to_send);
+
+            return self
+                .gas
+                .send_transaction(contract.client_ref(), contract_call.tx)
+                .await;
+        };
+
+        let contract =
+            VerifiedDepositor::new(self.atomic_swap_contract_address, self.signer_client());
+
+        let pubkey: [U256; 8] = calldata
+            .inputs
+            .get(..8)
+            .ok_or_else(|| eyre!("atomic-swap proof has too few public inputs"))?
+            .iter()
+            .map(biguint_to_u256)
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("slice has 8 elements");
+
+        let swap_proof = SwapProof {
+            a: calldata.a.map(|a| biguint_to_u256(&a)),
+            b: calldata.b.map(|b| b.map(|b| biguint_to_u256(&b))),
+            c: calldata.c.map(|c| biguint_to_u256(&c)),
+        };
+
+        let mut contract_call = contract.deposit_with_proof(
+            counterparty_ethereum_address,
+            swap_secret_hash,
+            lock_time,
+            pubkey,
+            swap_proof,
+        );
+        contract_call.tx.set_value(wei_to_send);
+
+        self.gas
+            .send_transaction(contract.client_ref(), contract_call.tx)
+            .await
+    }
+
+    async fn send_atomic_swap_tx_to_bitcoin(
+        &self,
+        sats_to_swap: u64,
+        escrow_pubkey: secp256k1::PublicKey,
+        secp_ctx: &Secp256k1<All>,
+    ) -> Result<BitcoinTxid> {
+        let revocation_pubkey = self.keys()?.bitcoin.public_key();
+
+        let taproot_descriptor = swap_descriptor(
+            escrow_pubkey,
+            revocation_pubkey,
+            self.swap_params.bitcoin_csv_delay,
+            self.bitcoin_backend.network(),
+            secp_ctx,
+        )?;
+
+        // We need it to easy get the address from the descriptor
+        let wallet = BitcoinWallet::new(
+            taproot_descriptor.clone(),
+            None,
+            self.bitcoin_backend.network(),
+            MemoryDatabase::new(),
+        )?;
+
+        let taproot_address = wallet.get_address(AddressIndex::New)?.address;
+
+        let tx_id = self
+            .send_sats_to_specified_address(sats_to_swap, taproot_address.clone())
+            .await
+            .wrap_err(format!(
+                "failed to send {} satoshis to {}",
+                sats_to_swap, taproot_address
+            ))?;
+
+        Ok(tx_id)
+    }
+
+    async fn send_sats_to_specified_address(
+        &self,
+        sats_amount: u64,
+        address: BitcoinAddress,
+    ) -> Result<BitcoinTxid> {
+        let bitcoin_signer = self.bitcoin_signer.clone();
+        let fallback_feerate = self.bitcoin_fallback_feerate;
+
+        let mut psbt = self
+            .bitcoin_backend
+            .with_wallet(move |bitcoin_wallet, bitcoin_client| {
+                bitcoin_wallet.sync(bitcoin_client, SyncOptions::default())?;
+
+                let (mut psbt, _details) = {
+                    let mut tx_builder = bitcoin_wallet.build_tx();
+
+                    let feerate = estimate_feerate(bitcoin_client, fallback_feerate)?;
+
+                    tx_builder
+                        .fee_rate(feerate)
+                        .add_recipient(address.script_pubkey(), sats_amount)
+                        .include_output_redeem_witness_script();
+                    tx_builder.finish()?
+                };
+
+                bitcoin_signer.sign_in_process(bitcoin_wallet, &mut psbt)?;
+
+                Ok(psbt)
+            })
+            .await?;
+
+        // The wallet is released while the external signer signs, and is locked again to
+        // finalize the signed PSBT.
+        self.bitcoin_signer
+            .sign_externally(&mut psbt, &self.cancellation)
+            .await?;
+
+        self.bitcoin_backend
+            .with_wallet(move |bitcoin_wallet, bitcoin_client| {
+                finalize_psbt(bitcoin_wallet, &mut psbt)?;
+
+                let txid = psbt.unsigned_tx.txid();
+
+                bitcoin_client.broadcast(&psbt.extract_tx())?;
+
+                Ok(txid)
+            })
+            .await
+    }
+
+    /// Polls the Bitcoin node for the taproot atomic-swap transaction until it appears, the
+    /// attempts run out or the swap is cancelled.
+    async fn check_atomic_swap_tx_appeared_on_bitcoin(
+        &self,
+        swap_pubkey: secp256k1::PublicKey,
+        revocation_pubkey_raw: secp256k1::PublicKey,
+    ) -> Result<bool> {
+        let escrow_pubkey = bitcoin::PublicKey::new(
+            swap_pubkey
+                .combine(&self.bitcoin_public_key()?)
+                .expect("It's impossible to fail for 2 different public keys"),
+        );
+        let revocation_pubkey = bitcoin::PublicKey::new(revocation_pubkey_raw);
+
+        let taproot_descriptor = bdk::descriptor!(tr(
+            escrow_pubkey,
+            and_v(v:pk(revocation_pubkey), older(self.swap_params.bitcoin_csv_delay))
+        ))?;
+
+        let mut wallet = Wallet::new(
+            taproot_descriptor,
+            None,
+            self.bitcoin_backend.network(),
+            MemoryDatabase::new(),
+        )?;
+
+        for _ in 0..=MAX_NUMBER_OF_ATTEMPTS_TO_SYNC {
+            // The wallet is moved to the blocking task and back, as it can't be shared.
+            let (synced_wallet, is_found) = cancellable(
+                &self.cancellation,
+                self.bitcoin_backend.with_client(move |bitcoin_client| {
+                    wallet
+                        .sync(bitcoin_client, SyncOptions::default())
+                        .wrap_err("failed to sync a BDK wallet")?;
+
+                    let unspent_utxos = wallet
+                        .list_unspent()
+                        .wrap_err("failed to retrieve unspent UTXOs from BDK wallet")?;
+
+                    Ok((wallet, !unspent_utxos.is_empty()))
+                }),
+            )
+            .await?;
+
+            if is_found {
+                // The wallet has only a taproot descriptor, so it is our transaction.
+                return Ok(true);
+            }
+            wallet = synced_wallet;
+
+            cancellable(&self.cancellation, async {
+                tokio::time::sleep(Duration::from_secs(DELAY_BETWEEN_SYNC_ATTEMPT_SEC)).await;
+                Ok(())
+            })
+            .await?;
+        }
+
+        Ok(false)
+    }
+}
+
+/// Encodes the snarkjs proof and public signals as the calldata for the Solidity verifier.
+fn swap_proof_calldata(proof_json: &str, pubsignals_json: &str) -> Result<SolidityCalldata> {
+    let proof = ZkProof::from_json(proof_json.as_bytes())?;
+    let public_inputs = PublicInputs::from_json(pubsignals_json.as_bytes())?;
+
+    Ok(SolidityCalldata::new(&proof, &public_inputs))
+}
+
+fn biguint_to_u256(value: &BigUint) -> U256 {
+    U256::from_big_endian(&value.to_bytes_be())
+}
+
+/// Returns the descriptor of the taprootized atomic-swap output the initiator funds: the escrow key
+/// path and the revocation script leaf compiled from its policy.
+fn swap_descriptor(
+    escrow_pubkey: secp256k1::PublicKey,
+    revocation_pubkey: secp256k1::PublicKey,
+    bitcoin_csv_delay: u32,
+    network: bitcoin::Network,
+    secp_ctx: &Secp256k1<All>,
+) -> Result<ExtendedDescriptor> {
+    let taptree_policy_str = &format!("and(older({bitcoin_csv_delay}),pk({revocation_pubkey}))");
+    let taptree_policy = Concrete::<String>::from_str(taptree_policy_str)?.compile()?;
+    let taptree = TapTree::Leaf(Arc::new(taptree_policy));
+
+    let (descriptor, _) = Descriptor::new_tr(escrow_pubkey.to_string(), Some(taptree))?
+        .to_string()
+        .into_wallet_descriptor(secp_ctx, network)?;
+
+    Ok(descriptor)
+}
+
+/// Returns the descriptor of the initiator's taprootized atomic-swap output that spends it
+/// through the revocation script leaf, `revocation_pubkey` with the `bitcoin_csv_delay`. The
+/// revocation key is public, so the refund can be signed by the external signer, and the
+/// in-process one is added with [`add_revocation_signer`].
+fn refund_descriptor(
+    escrow_pubkey: bitcoin::PublicKey,
+    revocation_pubkey: bitcoin::PublicKey,
+    bitcoin_csv_delay: u32,
+) -> Result<DescriptorTemplateOut> {
+    Ok(bdk::descriptor!(tr(
+        escrow_pubkey,
+        and_v(v:pk(revocation_pubkey), older(bitcoin_csv_delay))
+    ))?)
+}
+
+/// Adds the in-process signer of the revocation script leaf to the `wallet` of
+/// [`refund_descriptor`].
+fn add_revocation_signer(
+    wallet: &mut Wallet<MemoryDatabase>,
+    revocation_privkey: bitcoin::PrivateKey,
+) {
+    wallet.add_signer(
+        KeychainKind::External,
+        SignerOrdering::default(),
+        Arc::new(SignerWrapper::new(
+            revocation_privkey,
+            SignerContext::Tap {
+                is_internal_key: false,
+            },
+        )),
+    );
+}
+
+/// Builds the unsigned transaction that drains the `wallet` of [`refund_descriptor`] to the
+/// `recepient_address` through the revocation script leaf. The PSBT has the leaf script and its
+/// control block, so the external signer can sign it.
+fn build_refund_psbt(
+    wallet: &Wallet<MemoryDatabase>,
+    recepient_address: &BitcoinAddress,
+    feerate: FeeRate,
+) -> Result<PartiallySignedTransaction> {
+    let wallet_policy = wallet.policies(KeychainKind::External)?.unwrap();
+    let mut path = BTreeMap::new();
+    // The script path is the second item of the policy, after the escrow key.
+    path.insert(wallet_policy.id, vec![1]);
+
+    let (psbt, _details) = {
+        let mut builder = wallet.build_tx();
+
+        builder
+            .fee_rate(feerate)
+            .drain_wallet()
+            .drain_to(recepient_address.script_pubkey())
+            .policy_path(path, KeychainKind::External);
+
+        builder.finish()?
+    };
+
+    Ok(psbt)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use bdk::bitcoin::secp256k1::{Secp256k1, SecretKey, XOnlyPublicKey};
+    use bdk::bitcoin::{
+        self, Address, Network, OutPoint, PackedLockTime, Sequence, Transaction, TxIn, TxOut,
+    };
+    use bdk::database::{BatchOperations, MemoryDatabase, SyncTime};
+    use bdk::wallet::AddressIndex;
+    use bdk::{
+        BlockTime, FeeRate, KeychainKind, LocalUtxo, SignOptions, TransactionDetails, Wallet,
+    };
+    use tokio_util::sync::CancellationToken;
+
+    use super::{
+        add_revocation_signer, build_refund_psbt, refund_descriptor, swap_descriptor,
+        SwapParticipant,
+    };
+    use crate::signer::{finalize_psbt, parse_psbt, BitcoinSigner};
+
+    /// The participants are moved to the spawned tasks, so several swaps can run in parallel.
+    #[test]
+    fn test_swap_participant_is_thread_safe() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<SwapParticipant>();
+    }
+
+    /// The refund spends the swap output through the revocation script leaf with its CSV delay,
+    /// as the initiator doesn't have the escrow key. The unsigned PSBT is signed by the external
+    /// signer through the exported and imported files.
+    #[tokio::test]
+    async fn test_refund_psbt_spends_csv_leaf() {
+        let secp_ctx = Secp256k1::new();
+        let network = Network::Regtest;
+        let bitcoin_csv_delay = 144;
+
+        let escrow_pubkey = bitcoin::PublicKey::new(
+            SecretKey::from_slice(&[1; 32])
+                .unwrap()
+                .public_key(&secp_ctx),
+        );
+        let revocation_privkey =
+            bitcoin::PrivateKey::new(SecretKey::from_slice(&[2; 32]).unwrap(), network);
+        let revocation_pubkey = revocation_privkey.public_key(&secp_ctx);
+        let descriptor =
+            || refund_descriptor(escrow_pubkey, revocation_pubkey, bitcoin_csv_delay).unwrap();
+
+        let swap_address = Wallet::new(descriptor(), None, network, MemoryDatabase::new())
+            .unwrap()
+            .get_address(AddressIndex::Peek(0))
+            .unwrap()
+            .address;
+
+        // The refund wallet watches the same output that the initiator has funded.
+        let funded_address = Wallet::new(
+            swap_descriptor(
+                escrow_pubkey.inner,
+                revocation_pubkey.inner,
+                bitcoin_csv_delay,
+                network,
+                &secp_ctx,
+            )
+            .unwrap(),
+            None,
+            network,
+            MemoryDatabase::new(),
+        )
+        .unwrap()
+        .get_address(AddressIndex::Peek(0))
+        .unwrap()
+        .address;
+        assert_eq!(swap_address, funded_address);
+
+        // The swap output that the wallet would have found with a sync.
+        let swap_tx = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: 100_000,
+                script_pubkey: swap_address.script_pubkey(),
+            }],
+        };
+        let block_time = BlockTime {
+            height: 100,
+            timestamp: 1_700_000_000,
+        };
+
+        let mut database = MemoryDatabase::new();
+        database
+            .set_script_pubkey(&swap_address.script_pubkey(), KeychainKind::External, 0)
+            .unwrap();
+        database.set_raw_tx(&swap_tx).unwrap();
+        database
+            .set_tx(&TransactionDetails {
+                txid: swap_tx.txid(),
+                transaction: None,
+                received: 100_000,
+                sent: 0,
+                fee: None,
+                confirmation_time: Some(block_time.clone()),
+            })
+            .unwrap();
+        database
+            .set_utxo(&LocalUtxo {
+                outpoint: OutPoint::new(swap_tx.txid(), 0),
+                txout: swap_tx.output[0].clone(),
+                keychain: KeychainKind::External,
+                is_spent: false,
+            })
+            .unwrap();
+        database
+            .set_sync_time(SyncTime {
+                block_time: BlockTime {
+                    height: block_time.height + bitcoin_csv_delay,
+                    timestamp: block_time.timestamp,
+                },
+            })
+            .unwrap();
+
+        let wallet = Wallet::new(descriptor(), None, network, database).unwrap();
+        let recepient_address = Address::p2wpkh(&escrow_pubkey, network).unwrap();
+
+        let unsigned_psbt =
+            build_refund_psbt(&wallet, &recepient_address, FeeRate::from_sat_per_vb(1.0)).unwrap();
+
+        assert_eq!(unsigned_psbt.unsigned_tx.input.len(), 1);
+        assert_eq!(
+            unsigned_psbt.unsigned_tx.input[0].sequence,
+            Sequence(bitcoin_csv_delay)
+        );
+        assert_eq!(
+            unsigned_psbt.unsigned_tx.output[0].script_pubkey,
+            recepient_address.script_pubkey()
+        );
+        // The external signer gets the revocation leaf script with the control block that commits
+        // to the swap output's key.
+        let output_key =
+            XOnlyPublicKey::from_slice(&swap_address.script_pubkey().as_bytes()[2..]).unwrap();
+        let tap_scripts = &unsigned_psbt.inputs[0].tap_scripts;
+        assert_eq!(tap_scripts.len(), 1);
+        let (control_block, (leaf_script, _)) = tap_scripts.iter().next().unwrap();
+        assert!(control_block.verify_taproot_commitment(&secp_ctx, output_key, leaf_script));
+
+        let output = std::env::temp_dir().join(format!("{}-refund.psbt", std::process::id()));
+        let input = std::env::temp_dir().join(format!("{}-refund-signed.psbt", std::process::id()));
+        let bitcoin_signer = BitcoinSigner::Psbt {
+            output: Some(output.clone()),
+            input: Some(input.clone()),
+        };
+
+        // The external signer signs the exported PSBT without finalizing it.
+        let mut signer_wallet =
+            Wallet::new(descriptor(), None, network, MemoryDatabase::new()).unwrap();
+        add_revocation_signer(&mut signer_wallet, revocation_privkey);
+        let external_signer = async {
+            while !output.exists() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            let mut exported_psbt = parse_psbt(&fs::read(&output).unwrap()).unwrap();
+            assert_eq!(exported_psbt, unsigned_psbt);
+
+            let sign_options = SignOptions {
+                try_finalize: false,
+                ..Default::default()
+            };
+            signer_wallet
+                .sign(&mut exported_psbt, sign_options)
+                .unwrap();
+            fs::write(&input, exported_psbt.to_string()).unwrap();
+        };
+
+        let mut psbt = unsigned_psbt.clone();
+        let (signed, ()) = tokio::join!(
+            bitcoin_signer.sign_externally(&mut psbt, &CancellationToken::new()),
+            external_signer
+        );
+        fs::remove_file(&output).unwrap();
+        fs::remove_file(&input).unwrap();
+        signed.unwrap();
+
+        finalize_psbt(&wallet, &mut psbt).unwrap();
+        assert_eq!(psbt.unsigned_tx, unsigned_psbt.unsigned_tx);
+
+        // The script path spend's witness is the signature, the leaf script and the control block,
+        // while the key path spend's one is the signature only.
+        let witness = psbt.inputs[0].final_script_witness.as_ref().unwrap();
+        assert_eq!(witness.len(), 3);
+    }
+}
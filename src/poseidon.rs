@@ -0,0 +1,302 @@
+//! Poseidon hash over the BN254 scalar field with the circomlib parameters, the one the
+//! atomic-swap circuit and the `PoseidonUnit{N}L` libraries on Ethereum use.
+//!
+//! Round constants and the MDS matrix are derived with the Grain LFSR exactly as the reference
+//! `generate_parameters_grain.sage` script that circomlib's constants come from.
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+use eyre::{eyre, Result};
+use num::BigUint;
+
+/// Number of the full rounds, the same for every width.
+const FULL_ROUNDS: usize = 8;
+
+/// Number of the partial rounds for the state width `t = 2..=7`, as in circomlib. Only the widths
+/// `PoseidonUnit{1..6}L` are deployed for on Ethereum are supported.
+const PARTIAL_ROUNDS: [usize; 6] = [56, 57, 56, 60, 60, 63];
+
+/// Number of the 64-bit limbs the swap secret is split into before hashing.
+const SECRET_LIMBS: usize = 4;
+
+/// Bit size of the BN254 scalar field elements.
+const FIELD_BITS: usize = 254;
+
+/// Calculates the Poseidon hash of the swap secret exactly as the atomic-swap circuit and
+/// `Depositor._getSecretHash` do: the secret is split into 64-bit limbs that are hashed with
+/// `Poseidon(4)` starting from the least significant one.
+pub fn secret_hash(secret: &[u8; 32]) -> [u8; 32] {
+    static POSEIDON: OnceLock<Poseidon> = OnceLock::new();
+
+    let hash = POSEIDON
+        .get_or_init(|| Poseidon::new(SECRET_LIMBS).expect("Poseidon(4) is supported"))
+        .hash(&secret_limbs(secret))
+        .expect("secret is always split into 4 limbs");
+
+    fr_to_bytes(&hash)
+}
+
+/// Poseidon permutation parameters for a fixed number of inputs.
+pub struct Poseidon {
+    width: usize,
+    partial_rounds: usize,
+    round_constants: Vec<Fr>,
+    mds: Vec<Vec<Fr>>,
+}
+
+impl Poseidon {
+    /// Creates the hasher for `n_inputs` inputs, circomlib's `Poseidon(n_inputs)`.
+    pub fn new(n_inputs: usize) -> Result<Self> {
+        let width = n_inputs + 1;
+        let partial_rounds = *PARTIAL_ROUNDS
+            .get(n_inputs.wrapping_sub(1))
+            .ok_or_else(|| eyre!("unsupported number of Poseidon inputs: {n_inputs}"))?;
+
+        let mut grain = Grain::new(width, partial_rounds);
+
+        let round_constants = (0..(FULL_ROUNDS + partial_rounds) * width)
+            .map(|_| grain.next_field_element())
+            .collect();
+        let mds = grain.next_mds(width);
+
+        Ok(Self {
+            width,
+            partial_rounds,
+            round_constants,
+            mds,
+        })
+    }
+
+    pub fn hash(&self, inputs: &[Fr]) -> Result<Fr> {
+        if inputs.len() + 1 != self.width {
+            return Err(eyre!(
+                "invalid number of Poseidon inputs: expected {}, got {}",
+                self.width - 1,
+                inputs.len()
+            ));
+        }
+
+        let mut state = Vec::with_capacity(self.width);
+        state.push(Fr::zero());
+        state.extend_from_slice(inputs);
+
+        let rounds = FULL_ROUNDS + self.partial_rounds;
+        for (round, constants) in self.round_constants.chunks_exact(self.width).enumerate() {
+            for (element, constant) in state.iter_mut().zip(constants) {
+                *element += constant;
+            }
+
+            let is_full_round = round < FULL_ROUNDS / 2 || round >= rounds - FULL_ROUNDS / 2;
+            if is_full_round {
+                state.iter_mut().for_each(sbox);
+            } else {
+                sbox(&mut state[0]);
+            }
+
+            state = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(&state).map(|(m, s)| *m * s).sum())
+                .collect();
+        }
+
+        Ok(state[0])
+    }
+}
+
+fn sbox(element: &mut Fr) {
+    *element = element.pow([5u64]);
+}
+
+/// The Grain LFSR in self-shrinking mode the Poseidon parameters are sampled from.
+struct Grain {
+    state: VecDeque<bool>,
+}
+
+impl Grain {
+    fn new(width: usize, partial_rounds: usize) -> Self {
+        let mut state = VecDeque::with_capacity(80);
+        let mut push_bits = |value: usize, bits: usize| {
+            state.extend((0..bits).rev().map(|i| (value >> i) & 1 == 1));
+        };
+
+        // Prime field, x^alpha S-box, field size, t, R_F, R_P and the 30 padding bits.
+        push_bits(1, 2);
+        push_bits(0, 4);
+        push_bits(FIELD_BITS, 12);
+        push_bits(width, 12);
+        push_bits(FULL_ROUNDS, 10);
+        push_bits(partial_rounds, 10);
+        push_bits((1 << 30) - 1, 30);
+
+        let mut grain = Self { state };
+        for _ in 0..160 {
+            grain.next_raw_bit();
+        }
+
+        grain
+    }
+
+    fn next_raw_bit(&mut self) -> bool {
+        let new_bit = [62, 51, 38, 23, 13, 0]
+            .iter()
+            .fold(false, |acc, &i| acc ^ self.state[i]);
+
+        self.state.pop_front();
+        self.state.push_back(new_bit);
+
+        new_bit
+    }
+
+    /// Returns the next bit of the self-shrinking generator: the second bit of a pair is output
+    /// only if the first one is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let first = self.next_raw_bit();
+            let second = self.next_raw_bit();
+            if first {
+                return second;
+            }
+        }
+    }
+
+    fn next_uint(&mut self) -> BigUint {
+        let mut bytes = vec![0u8; FIELD_BITS.div_ceil(8)];
+        for i in (0..FIELD_BITS).rev() {
+            if self.next_bit() {
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+
+        BigUint::from_bytes_le(&bytes)
+    }
+
+    /// Samples a field element rejecting the values outside the field.
+    fn next_field_element(&mut self) -> Fr {
+        let modulus = BigUint::from(Fr::MODULUS);
+        loop {
+            let value = self.next_uint();
+            if value < modulus {
+                return Fr::from(value);
+            }
+        }
+    }
+
+    /// Samples the Cauchy MDS matrix `M[i][j] = 1 / (x[i] + y[j])`.
+    fn next_mds(&mut self, width: usize) -> Vec<Vec<Fr>> {
+        loop {
+            let values: Vec<Fr> = (0..2 * width).map(|_| Fr::from(self.next_uint())).collect();
+
+            let has_duplicates = values
+                .iter()
+                .enumerate()
+                .any(|(i, value)| values[..i].contains(value));
+            if has_duplicates {
+                continue;
+            }
+
+            let (xs, ys) = values.split_at(width);
+            let mds: Option<Vec<Vec<Fr>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| (*x + y).inverse()).collect())
+                .collect();
+
+            if let Some(mds) = mds {
+                return mds;
+            }
+        }
+    }
+}
+
+/// Splits the big-endian secret into 64-bit limbs, the least significant one first.
+fn secret_limbs(secret: &[u8; 32]) -> [Fr; SECRET_LIMBS] {
+    let mut limbs = [Fr::zero(); SECRET_LIMBS];
+    for (limb, chunk) in limbs.iter_mut().zip(secret.rchunks_exact(8)) {
+        *limb = Fr::from(u64::from_be_bytes(
+            chunk.try_into().expect("chunk is always 8 bytes"),
+        ));
+    }
+
+    limbs
+}
+
+fn fr_to_bytes(value: &Fr) -> [u8; 32] {
+    value
+        .into_bigint()
+        .to_bytes_be()
+        .try_into()
+        .expect("BN254 scalar is always 32 bytes")
+}
+
+#[cfg(test)]
+mod test {
+    use ark_bn254::Fr;
+
+    use super::{fr_to_bytes, secret_hash, Poseidon};
+
+    /// Test vectors shared with the contracts' tests.
+    const TEST_VECTORS: &str = include_str!("../contracts/test/data/poseidon-vectors.json");
+
+    #[derive(serde::Deserialize)]
+    struct TestVector {
+        secret: String,
+        limbs: [String; 4],
+        hash: String,
+    }
+
+    fn decode32(value: &str) -> [u8; 32] {
+        hex::decode(value.trim_start_matches("0x"))
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_circomlib_vectors() {
+        // circomlibjs: poseidon([1, 2])
+        let hash = Poseidon::new(2)
+            .unwrap()
+            .hash(&[1u64, 2].map(Fr::from))
+            .unwrap();
+        assert_eq!(
+            hex::encode(fr_to_bytes(&hash)),
+            "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"
+        );
+
+        // circomlibjs: poseidon([1, 2, 3, 4])
+        let hash = Poseidon::new(4)
+            .unwrap()
+            .hash(&[1u64, 2, 3, 4].map(Fr::from))
+            .unwrap();
+        assert_eq!(
+            hex::encode(fr_to_bytes(&hash)),
+            "299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465"
+        );
+    }
+
+    #[test]
+    fn test_secret_hash_vectors() {
+        let vectors: Vec<TestVector> = serde_json::from_str(TEST_VECTORS).unwrap();
+        assert!(!vectors.is_empty());
+
+        let poseidon = Poseidon::new(4).unwrap();
+        for vector in vectors {
+            let secret = decode32(&vector.secret);
+            let expected_hash = decode32(&vector.hash);
+
+            let limbs = vector
+                .limbs
+                .map(|limb| Fr::from(u64::from_str_radix(&limb[2..], 16).unwrap()));
+            assert_eq!(fr_to_bytes(&poseidon.hash(&limbs).unwrap()), expected_hash);
+
+            assert_eq!(secret_hash(&secret), expected_hash);
+
+            let mut wrong_secret = secret;
+            wrong_secret[31] ^= 1;
+            assert_ne!(secret_hash(&wrong_secret), expected_hash);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use bdk::bitcoin::secp256k1;
+use eyre::{eyre, Context, Result};
+use num::{BigInt, BigUint, Zero};
+use rapidsnark::PublicInputs;
+use std::str::FromStr;
+
+use crate::{u256_to_u64array, u64array_to_u256};
+
+/// Number of the 64-bit limbs of a secp256k1 coordinate in the Atomic-swap ZK proof public
+/// signals.
+const PUBKEY_COORDINATE_LIMBS: usize = 4;
+
+/// Number of the Atomic-swap ZK proof public signals: the pubkey's X and Y limbs and the secret
+/// hash.
+pub const PUBSIGNALS_NUMBER: usize = 2 * PUBKEY_COORDINATE_LIMBS + 1;
+
+/// Public signals of the Atomic-swap ZK proof in the order the circuit outputs them: the swap
+/// pubkey `K = k * G` as the little-endian 64-bit limbs of its X and then Y, followed by the
+/// Poseidon hash of `k`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomicSwapPublicSignals {
+    pub pubkey: secp256k1::PublicKey,
+    pub secret_hash: [u8; 32],
+}
+
+impl AtomicSwapPublicSignals {
+    /// Parses the snarkjs `public.json` content.
+    pub fn from_json(pubsignals_json: &str) -> Result<Self> {
+        let pubsignals: Vec<String> = serde_json::from_str(pubsignals_json)
+            .wrap_err("failed to parse public signals from json")?;
+
+        Self::from_str_slice(&pubsignals)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.to_str_vec()).expect("Vec<String> is always serializable")
+    }
+
+    /// Parses the public signals from their decimal representation.
+    pub fn from_str_slice(pubsignals: &[String]) -> Result<Self> {
+        let pubsignals = pubsignals
+            .iter()
+            .map(|signal| BigUint::from_str(signal))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("failed to parse public signal as decimal number")?;
+
+        Self::from_biguint_slice(&pubsignals)
+    }
+
+    pub fn to_str_vec(&self) -> Vec<String> {
+        self.to_biguint_vec()
+            .iter()
+            .map(BigUint::to_string)
+            .collect()
+    }
+
+    pub fn from_public_inputs(public_inputs: &[Fr]) -> Result<Self> {
+        let pubsignals: Vec<BigUint> = public_inputs
+            .iter()
+            .map(|input| input.into_bigint().into())
+            .collect();
+
+        Self::from_biguint_slice(&pubsignals)
+    }
+
+    /// Converts the public signals to the proof public inputs. Fails if the secret hash is not in
+    /// the BN254 scalar field, which is never the case for the hash calculated by the circuit.
+    pub fn to_public_inputs(&self) -> Result<PublicInputs> {
+        self.to_biguint_vec()
+            .into_iter()
+            .map(|signal| {
+                Fr::from_bigint(
+                    signal
+                        .try_into()
+                        .map_err(|_| eyre!("public signal is greater than 256 bits"))?,
+                )
+                .ok_or_else(|| eyre!("public signal is outside the BN254 scalar field"))
+            })
+            .collect()
+    }
+
+    fn from_biguint_slice(pubsignals: &[BigUint]) -> Result<Self> {
+        if pubsignals.len() != PUBSIGNALS_NUMBER {
+            return Err(eyre!(
+                "invalid number of public signals: expected {PUBSIGNALS_NUMBER}, got {}",
+                pubsignals.len()
+            ));
+        }
+
+        let (key_x, rest) = pubsignals.split_at(PUBKEY_COORDINATE_LIMBS);
+        let (key_y, secret_hash) = rest.split_at(PUBKEY_COORDINATE_LIMBS);
+
+        // Public key prefix 0x04
+        let mut key_raw = [0u8; 65];
+        key_raw[0] = 0x04;
+        key_raw[1..33].copy_from_slice(&limbs_to_bytes(key_x).wrap_err("invalid pubkey's X")?);
+        key_raw[33..].copy_from_slice(&limbs_to_bytes(key_y).wrap_err("invalid pubkey's Y")?);
+
+        let pubkey = secp256k1::PublicKey::from_slice(&key_raw)
+            .wrap_err("public signals' pubkey is not on secp256k1")?;
+
+        // The circuit outputs the hash as a BN254 scalar, so any other value can't be proven.
+        if secret_hash[0] >= BigUint::from(Fr::MODULUS) {
+            return Err(eyre!("secret hash is outside the BN254 scalar field"));
+        }
+        let secret_hash = to_bytes32(&secret_hash[0]).expect("BN254 scalar always fits 32 bytes");
+
+        Ok(Self {
+            pubkey,
+            secret_hash,
+        })
+    }
+
+    fn to_biguint_vec(&self) -> Vec<BigUint> {
+        let key_raw = self.pubkey.serialize_uncompressed();
+
+        let mut result = Vec::with_capacity(PUBSIGNALS_NUMBER);
+        result.extend(bytes_to_limbs(&key_raw[1..33]));
+        result.extend(bytes_to_limbs(&key_raw[33..]));
+        result.push(BigUint::from_bytes_be(&self.secret_hash));
+
+        result
+    }
+}
+
+/// Converts little-endian 64-bit limbs into a big-endian 32-byte scalar.
+fn limbs_to_bytes(limbs: &[BigUint]) -> Result<[u8; 32]> {
+    let mut u64array = [0u64; PUBKEY_COORDINATE_LIMBS];
+    for (limb, value) in u64array.iter_mut().zip(limbs) {
+        *limb = u64::try_from(value).map_err(|_| eyre!("limb exceeds 64 bits: {value}"))?;
+    }
+
+    let scalar = u64array_to_u256(u64array)
+        .to_biguint()
+        .expect("u256 built from u64 limbs is never negative");
+
+    Ok(to_bytes32(&scalar).expect("u256 built from 4 u64 limbs always fits 32 bytes"))
+}
+
+/// Converts a big-endian 32-byte scalar into little-endian 64-bit limbs.
+fn bytes_to_limbs(bytes: &[u8]) -> impl Iterator<Item = BigUint> {
+    u256_to_u64array(BigInt::from_bytes_be(num::bigint::Sign::Plus, bytes))
+        .expect("32 bytes always fit u256")
+        .into_iter()
+        .map(BigUint::from)
+}
+
+/// Returns the big-endian representation of the value left-padded with zeros to 32 bytes, or
+/// [`None`] if it doesn't fit.
+pub fn to_bytes32(value: &BigUint) -> Option<[u8; 32]> {
+    if value.is_zero() {
+        return Some([0u8; 32]);
+    }
+
+    let bytes = value.to_bytes_be();
+    if bytes.len() > 32 {
+        return None;
+    }
+
+    let mut result = [0u8; 32];
+    result[32 - bytes.len()..].copy_from_slice(&bytes);
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use num::{bigint::Sign, BigInt, BigUint};
+    use proptest::prelude::*;
+
+    use super::{to_bytes32, AtomicSwapPublicSignals, PUBSIGNALS_NUMBER};
+    use crate::{u256_to_u64array, u64array_to_u256};
+
+    /// BN254 scalar field modulus, the bound of the secret hash calculated by the circuit.
+    const BN254_SCALAR_MODULUS: &str =
+        "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+    fn public_signals(secret: [u8; 32], secret_hash: [u8; 32]) -> Option<AtomicSwapPublicSignals> {
+        let secret_key = SecretKey::from_slice(&secret).ok()?;
+
+        Some(AtomicSwapPublicSignals {
+            pubkey: secret_key.public_key(&Secp256k1::new()),
+            secret_hash,
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_u64array_round_trip(limbs in any::<[u64; 4]>()) {
+            let u256 = u64array_to_u256(limbs);
+
+            prop_assert_eq!(u256_to_u64array(u256), Some(limbs));
+        }
+
+        #[test]
+        fn test_u256_round_trip(bytes in any::<[u8; 32]>()) {
+            let u256 = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            let limbs = u256_to_u64array(u256.clone()).unwrap();
+
+            prop_assert_eq!(u64array_to_u256(limbs), u256);
+        }
+
+        #[test]
+        fn test_str_round_trip(
+            secret in any::<[u8; 32]>(),
+            secret_hash in any::<[u8; 32]>(),
+            leading_zeros in 0usize..=32,
+        ) {
+            let mut secret_hash = secret_hash;
+            // Keep the hash below the BN254 scalar field modulus.
+            secret_hash[0] &= 0x1f;
+            secret_hash[..leading_zeros].fill(0);
+            let Some(pubsignals) = public_signals(secret, secret_hash) else {
+                return Ok(());
+            };
+
+            let str_vec = pubsignals.to_str_vec();
+            prop_assert_eq!(str_vec.len(), PUBSIGNALS_NUMBER);
+
+            // Limbs must be the ones u256_to_u64array produces for the pubkey's coordinates.
+            let key_raw = pubsignals.pubkey.serialize_uncompressed();
+            let key_x = u256_to_u64array(BigInt::from_bytes_be(Sign::Plus, &key_raw[1..33])).unwrap();
+            let key_x_str: Vec<String> = key_x.iter().map(u64::to_string).collect();
+            prop_assert_eq!(&str_vec[..4], key_x_str.as_slice());
+
+            let parsed = AtomicSwapPublicSignals::from_json(&pubsignals.to_json()).unwrap();
+            prop_assert_eq!(parsed, pubsignals);
+        }
+
+        #[test]
+        fn test_public_inputs_round_trip(
+            secret in any::<[u8; 32]>(),
+            secret_hash in any::<[u8; 32]>(),
+        ) {
+            let mut secret_hash = secret_hash;
+            // Keep the hash below the BN254 scalar field modulus.
+            secret_hash[0] &= 0x1f;
+            let Some(pubsignals) = public_signals(secret, secret_hash) else {
+                return Ok(());
+            };
+
+            let public_inputs = pubsignals.to_public_inputs().unwrap();
+            let parsed = AtomicSwapPublicSignals::from_public_inputs(&public_inputs).unwrap();
+            prop_assert_eq!(parsed, pubsignals);
+        }
+    }
+
+    #[test]
+    fn test_secret_hash_with_leading_zeros() {
+        let mut pubsignals = public_signals([1u8; 32], [0u8; 32]).unwrap().to_str_vec();
+        pubsignals[8] = "1".to_string();
+
+        let parsed = AtomicSwapPublicSignals::from_str_slice(&pubsignals).unwrap();
+
+        let mut expected = [0u8; 32];
+        expected[31] = 1;
+        assert_eq!(parsed.secret_hash, expected);
+    }
+
+    #[test]
+    fn test_invalid_public_signals() {
+        let pubsignals = public_signals([1u8; 32], [0u8; 32]).unwrap().to_str_vec();
+
+        // Wrong length.
+        assert!(AtomicSwapPublicSignals::from_str_slice(&pubsignals[..8]).is_err());
+        assert!(AtomicSwapPublicSignals::from_str_slice(&[]).is_err());
+
+        // Limb doesn't fit u64.
+        let mut invalid = pubsignals.clone();
+        invalid[0] = (BigUint::from(u64::MAX) + 1u8).to_string();
+        assert!(AtomicSwapPublicSignals::from_str_slice(&invalid).is_err());
+
+        // Pubkey is not on the curve.
+        let mut invalid = pubsignals.clone();
+        invalid[4] = "0".to_string();
+        assert!(AtomicSwapPublicSignals::from_str_slice(&invalid).is_err());
+
+        // Secret hash doesn't fit 32 bytes.
+        let mut invalid = pubsignals.clone();
+        invalid[8] = (BigUint::from(1u8) << 256u32).to_string();
+        assert!(AtomicSwapPublicSignals::from_str_slice(&invalid).is_err());
+
+        // Secret hash is outside the scalar field.
+        let mut invalid = pubsignals.clone();
+        invalid[8] = BN254_SCALAR_MODULUS.to_string();
+        assert!(AtomicSwapPublicSignals::from_str_slice(&invalid).is_err());
+
+        let pubsignals = AtomicSwapPublicSignals::from_str_slice(&pubsignals).unwrap();
+        let hash = BN254_SCALAR_MODULUS.parse::<BigUint>().unwrap();
+        let invalid = AtomicSwapPublicSignals {
+            secret_hash: to_bytes32(&hash).unwrap(),
+            ..pubsignals
+        };
+        assert!(invalid.to_public_inputs().is_err());
+    }
+}
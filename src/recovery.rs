@@ -0,0 +1,443 @@
+//! Recovery of the swap secret revealed by the counterparty's withdraw.
+//!
+//! The `Withdrawn` event may be missed if the participant was offline when the counterparty
+//! withdrew. The deposit's `isWithdrawn` flag in the contract state tells whether the deposit has
+//! been closed, and then the secret is recovered from the historical `Withdrawn` logs or, if the
+//! node doesn't serve them, from the input of the `withdraw` transaction sent directly to the
+//! contract. The flag is set by the sender's `restore` as well, which doesn't reveal the secret,
+//! so the `Restored` logs and the `restore` transactions are looked for too.
+
+use ethers::abi::AbiDecode;
+use ethers::providers::Middleware;
+use ethers::types::{BlockNumber, Bytes, TxHash};
+use eyre::{eyre, Context, Result};
+use futures_util::future::try_join_all;
+
+use crate::depositor_contract::{Depositor, DepositorCalls, DepositorEvents};
+use crate::relayed_depositor_contract::RelayedDepositorCalls;
+use crate::secret::SwapSecret;
+
+/// Number of blocks requested in a single `eth_getLogs` call, as the nodes limit the range.
+const LOGS_SCAN_BLOCKS: u64 = 5_000;
+
+/// Maximum number of blocks whose transactions are scanned if the logs aren't served, as every
+/// block is requested separately.
+const MAX_SCANNED_BLOCKS: u64 = 10_000;
+
+/// Number of blocks requested concurrently when scanning the transactions.
+const BLOCKS_BATCH_SIZE: u64 = 16;
+
+/// How the deposit has been closed.
+enum DepositClosure {
+    /// Withdrawn by the recipient with the revealed secret.
+    Withdrawn(SwapSecret),
+    /// Restored to the sender after the lock time.
+    Restored,
+}
+
+/// Returns the secret of the deposit with the `secret_hash` if it has been withdrawn after
+/// `from_block`, or [`None`] if the deposit hasn't been made or withdrawn yet. Fails if the
+/// deposit has been restored to the sender, as the secret is never revealed then.
+pub async fn recover_swap_secret<M: Middleware + 'static>(
+    contract: &Depositor<M>,
+    secret_hash: [u8; 32],
+    from_block: u64,
+) -> Result<Option<SwapSecret>> {
+    let (_, _, _, _, is_withdrawn) = contract.deposits(secret_hash).call().await?;
+    if !is_withdrawn {
+        return Ok(None);
+    }
+
+    let latest_block = contract
+        .client_ref()
+        .get_block_number()
+        .await
+        .wrap_err("failed to get latest Ethereum block")?
+        .as_u64();
+
+    let closure = match closure_from_logs(contract, secret_hash, from_block, latest_block).await {
+        Ok(Some(closure)) => closure,
+        Ok(None) => closure_from_blocks(contract, secret_hash, from_block, latest_block).await?,
+        Err(err) => {
+            println!("| Failed to scan deposit events, scanning transactions: {err:#}");
+            closure_from_blocks(contract, secret_hash, from_block, latest_block).await?
+        }
+    };
+
+    match closure {
+        DepositClosure::Withdrawn(swap_secret) => Ok(Some(swap_secret)),
+        DepositClosure::Restored => Err(eyre!(
+            "deposit has been restored to the sender, so the swap secret hasn't been revealed"
+        )),
+    }
+}
+
+/// Returns the secret from the input of the `withdraw` transaction.
+pub async fn recover_swap_secret_from_tx<M: Middleware + 'static>(
+    client: &M,
+    tx_hash: TxHash,
+    secret_hash: [u8; 32],
+) -> Result<SwapSecret> {
+    let tx = client
+        .get_transaction(tx_hash)
+        .await
+        .wrap_err("failed to get Ethereum transaction")?
+        .ok_or_else(|| eyre!("transaction {tx_hash:?} is not found"))?;
+
+    swap_secret_from_calldata(&tx.input, secret_hash)
+        .ok_or_else(|| eyre!("transaction {tx_hash:?} doesn't withdraw the deposit"))
+}
+
+/// Decodes the `withdraw` or `withdrawWithSignature` call and returns its secret if it matches the
+/// `secret_hash`.
+pub fn swap_secret_from_calldata(input: &Bytes, secret_hash: [u8; 32]) -> Option<SwapSecret> {
+    let secret = match DepositorCalls::decode(input) {
+        Ok(DepositorCalls::Withdraw(call)) => call.secret,
+        Ok(_) => return None,
+        Err(_) => match RelayedDepositorCalls::decode(input).ok()? {
+            RelayedDepositorCalls::WithdrawWithSignature(call) => call.secret,
+            _ => return None,
+        },
+    };
+
+    SwapSecret::from_u256(secret)
+        .ok()
+        .filter(|swap_secret| swap_secret.hash() == secret_hash)
+}
+
+/// Decodes the call closing the deposit with the `secret_hash`.
+fn closure_from_calldata(input: &Bytes, secret_hash: [u8; 32]) -> Option<DepositClosure> {
+    match DepositorCalls::decode(input) {
+        Ok(DepositorCalls::Restore(call)) if call.secret_hash == secret_hash => {
+            Some(DepositClosure::Restored)
+        }
+        _ => swap_secret_from_calldata(input, secret_hash).map(DepositClosure::Withdrawn),
+    }
+}
+
+async fn closure_from_logs<M: Middleware + 'static>(
+    contract: &Depositor<M>,
+    secret_hash: [u8; 32],
+    from_block: u64,
+    to_block: u64,
+) -> Result<Option<DepositClosure>> {
+    let mut start_block = from_block;
+    while start_block <= to_block {
+        let end_block = to_block.min(start_block + LOGS_SCAN_BLOCKS - 1);
+
+        let events = contract
+            .events()
+            .from_block(start_block)
+            .to_block(end_block)
+            .query()
+            .await?;
+        for event in events {
+            match event {
+                DepositorEvents::WithdrawnFilter(withdrawal)
+                    if withdrawal.secret_hash == secret_hash =>
+                {
+                    let swap_secret = SwapSecret::from_u256(withdrawal.secret)
+                        .wrap_err("revealed secret is not a valid swap secret")?;
+                    if swap_secret.hash() != secret_hash {
+                        return Err(eyre!("revealed secret doesn't match the swap secret hash"));
+                    }
+
+                    return Ok(Some(DepositClosure::Withdrawn(swap_secret)));
+                }
+                DepositorEvents::RestoredFilter(restoration)
+                    if restoration.secret_hash == secret_hash =>
+                {
+                    return Ok(Some(DepositClosure::Restored));
+                }
+                _ => {}
+            }
+        }
+
+        start_block = end_block + 1;
+    }
+
+    Ok(None)
+}
+
+async fn is_succeeded<M: Middleware + 'static>(client: &M, tx_hash: TxHash) -> Result<bool> {
+    let receipt = client
+        .get_transaction_receipt(tx_hash)
+        .await
+        .wrap_err("failed to get Ethereum transaction receipt")?
+        .ok_or_else(|| eyre!("receipt of transaction {tx_hash:?} is not found"))?;
+
+    Ok(receipt.status == Some(1.into()))
+}
+
+/// Scans the transactions to the contract from the latest block back to `from_block`, at most
+/// [`MAX_SCANNED_BLOCKS`] of them. The withdraws made through another contract are not found this
+/// way.
+async fn closure_from_blocks<M: Middleware + 'static>(
+    contract: &Depositor<M>,
+    secret_hash: [u8; 32],
+    from_block: u64,
+    to_block: u64,
+) -> Result<DepositClosure> {
+    let scanned_blocks = (to_block + 1).saturating_sub(from_block);
+    if scanned_blocks > MAX_SCANNED_BLOCKS {
+        return Err(eyre!(
+            "deposit has been closed, but its event is not found and {scanned_blocks} blocks are \
+             too many to scan, recover the secret from the withdraw transaction instead"
+        ));
+    }
+
+    let client = contract.client_ref();
+    let mut end_block = to_block;
+    while end_block >= from_block {
+        let start_block = from_block.max((end_block + 1).saturating_sub(BLOCKS_BATCH_SIZE));
+
+        let blocks = try_join_all(
+            (start_block..=end_block)
+                .rev()
+                .map(|block_number| async move {
+                    client
+                        .get_block_with_txs(BlockNumber::Number(block_number.into()))
+                        .await
+                        .wrap_err_with(|| format!("failed to get Ethereum block {block_number}"))?
+                        .ok_or_else(|| eyre!("Ethereum block {block_number} is absent"))
+                }),
+        )
+        .await?;
+
+        let txs = blocks
+            .iter()
+            .flat_map(|block| block.transactions.iter().rev())
+            .filter(|tx| tx.to == Some(contract.address()));
+        for tx in txs {
+            match closure_from_calldata(&tx.input, secret_hash) {
+                // The secret is revealed by the call even if it has reverted.
+                Some(closure @ DepositClosure::Withdrawn(_)) => return Ok(closure),
+                // The reverted `restore`, e.g. before the lock time, hasn't closed the deposit.
+                Some(DepositClosure::Restored) if is_succeeded(client, tx.hash).await? => {
+                    return Ok(DepositClosure::Restored)
+                }
+                _ => {}
+            }
+        }
+
+        if start_block == 0 {
+            break;
+        }
+        end_block = start_block - 1;
+    }
+
+    Err(eyre!(
+        "deposit has been withdrawn or restored, but the closing transaction is not found"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use ethers::abi::{AbiEncode, Token};
+    use ethers::contract::EthEvent;
+    use ethers::providers::{MockProvider, Provider};
+    use ethers::types::{
+        Address, Block, Bytes, Filter, Log, Transaction, TransactionReceipt, H256, U256, U64,
+    };
+
+    use super::{
+        closure_from_blocks, closure_from_calldata, recover_swap_secret, swap_secret_from_calldata,
+        DepositClosure, MAX_SCANNED_BLOCKS,
+    };
+    use crate::depositor_contract::{Depositor, RestoreCall, WithdrawCall, WithdrawnFilter};
+    use crate::relayed_depositor_contract::WithdrawWithSignatureCall;
+    use crate::secret::SwapSecret;
+
+    #[test]
+    fn test_swap_secret_from_calldata() {
+        let swap_secret = SwapSecret::random(&mut rand::thread_rng());
+        let secret_hash = swap_secret.hash();
+
+        let withdraw = Bytes::from(
+            WithdrawCall {
+                secret: swap_secret.to_u256(),
+            }
+            .encode(),
+        );
+        assert!(swap_secret_from_calldata(&withdraw, secret_hash) == Some(swap_secret));
+        assert!(swap_secret_from_calldata(&withdraw, [0x11; 32]).is_none());
+
+        let relayed_withdraw = Bytes::from(
+            WithdrawWithSignatureCall {
+                secret: swap_secret.to_u256(),
+                fee: 1.into(),
+                deadline: 2.into(),
+                signature: Bytes::from(vec![0x42; 65]),
+            }
+            .encode(),
+        );
+        assert!(swap_secret_from_calldata(&relayed_withdraw, secret_hash) == Some(swap_secret));
+
+        let restore = Bytes::from(RestoreCall { secret_hash }.encode());
+        assert!(swap_secret_from_calldata(&restore, secret_hash).is_none());
+        assert!(swap_secret_from_calldata(&Bytes::from(vec![0x42; 4]), secret_hash).is_none());
+    }
+
+    #[test]
+    fn test_closure_from_calldata() {
+        let swap_secret = SwapSecret::random(&mut rand::thread_rng());
+        let secret_hash = swap_secret.hash();
+
+        let restore = Bytes::from(RestoreCall { secret_hash }.encode());
+        assert!(matches!(
+            closure_from_calldata(&restore, secret_hash),
+            Some(DepositClosure::Restored)
+        ));
+        assert!(closure_from_calldata(&restore, [0x11; 32]).is_none());
+
+        let withdraw = Bytes::from(
+            WithdrawCall {
+                secret: swap_secret.to_u256(),
+            }
+            .encode(),
+        );
+        assert!(matches!(
+            closure_from_calldata(&withdraw, secret_hash),
+            Some(DepositClosure::Withdrawn(secret)) if secret == swap_secret
+        ));
+    }
+
+    fn mocked_contract() -> (Depositor<Provider<MockProvider>>, MockProvider) {
+        let (client, mock) = Provider::mocked();
+        let contract = Depositor::new(Address::repeat_byte(0xde), Arc::new(client));
+
+        (contract, mock)
+    }
+
+    fn block(number: u64, input: Option<Bytes>) -> Block<Transaction> {
+        let transactions = input
+            .map(|input| Transaction {
+                hash: H256::from_low_u64_be(number),
+                to: Some(Address::repeat_byte(0xde)),
+                input,
+                ..Default::default()
+            })
+            .into_iter()
+            .collect();
+
+        Block {
+            number: Some(number.into()),
+            hash: Some(H256::from_low_u64_be(number)),
+            transactions,
+            ..Default::default()
+        }
+    }
+
+    fn receipt(status: u64) -> TransactionReceipt {
+        TransactionReceipt {
+            status: Some(status.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_closure_from_blocks() {
+        let swap_secret = SwapSecret::random(&mut rand::thread_rng());
+        let secret_hash = swap_secret.hash();
+        let restore = Bytes::from(RestoreCall { secret_hash }.encode());
+        let withdraw = Bytes::from(
+            WithdrawCall {
+                secret: swap_secret.to_u256(),
+            }
+            .encode(),
+        );
+
+        // The blocks are requested from the latest one, and the mocked responses are returned in
+        // the reverse order. The `restore` of block 12 has reverted, so the withdraw of block 10
+        // is found.
+        let (contract, mock) = mocked_contract();
+        mock.push(receipt(0)).unwrap();
+        mock.push(block(10, Some(withdraw))).unwrap();
+        mock.push(block(11, None)).unwrap();
+        mock.push(block(12, Some(restore.clone()))).unwrap();
+
+        let closure = closure_from_blocks(&contract, secret_hash, 10, 12)
+            .await
+            .unwrap();
+        assert!(matches!(closure, DepositClosure::Withdrawn(secret) if secret == swap_secret));
+
+        let (contract, mock) = mocked_contract();
+        mock.push(receipt(1)).unwrap();
+        mock.push(block(10, None)).unwrap();
+        mock.push(block(11, Some(restore))).unwrap();
+
+        let closure = closure_from_blocks(&contract, secret_hash, 10, 11)
+            .await
+            .unwrap();
+        assert!(matches!(closure, DepositClosure::Restored));
+
+        let (contract, mock) = mocked_contract();
+        mock.push(block(10, None)).unwrap();
+
+        assert!(closure_from_blocks(&contract, secret_hash, 10, 10)
+            .await
+            .is_err());
+
+        // Too many blocks are rejected before any request.
+        let (contract, _mock) = mocked_contract();
+        assert!(
+            closure_from_blocks(&contract, secret_hash, 1, MAX_SCANNED_BLOCKS + 1)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_withdrawal_before_start() {
+        let swap_secret = SwapSecret::random(&mut rand::thread_rng());
+        let secret_hash = swap_secret.hash();
+
+        // The deposit is sent after block 10 and withdrawn in block 15, before the listener that
+        // has started at block 20, so the logs are requested since the deposit's block.
+        let (deposit_block, withdraw_block, latest_block) = (10u64, 15u64, 30u64);
+        let withdrawn = Log {
+            address: Address::repeat_byte(0xde),
+            topics: vec![
+                WithdrawnFilter::signature(),
+                H256::from(Address::repeat_byte(0x42)),
+            ],
+            data: ethers::abi::encode(&[
+                Token::Uint(U256::exp10(18)),
+                Token::Uint(swap_secret.to_u256()),
+                Token::FixedBytes(secret_hash.to_vec()),
+            ])
+            .into(),
+            block_number: Some(withdraw_block.into()),
+            ..Default::default()
+        };
+        let deposit = Bytes::from(ethers::abi::encode(&[
+            Token::Address(Address::repeat_byte(0x11)),
+            Token::Address(Address::repeat_byte(0x42)),
+            Token::Uint(U256::exp10(18)),
+            Token::Uint(U256::from(3600)),
+            Token::Bool(true),
+        ]));
+
+        let (contract, mock) = mocked_contract();
+        mock.push::<Vec<Log>, _>(vec![withdrawn]).unwrap();
+        mock.push::<U64, _>(U64::from(latest_block)).unwrap();
+        mock.push::<Bytes, _>(deposit).unwrap();
+
+        let recovered = recover_swap_secret(&contract, secret_hash, deposit_block)
+            .await
+            .unwrap();
+        assert!(recovered == Some(swap_secret));
+
+        let filter: Filter = contract
+            .events()
+            .from_block(deposit_block)
+            .to_block(latest_block)
+            .filter;
+        let call = contract.deposits(secret_hash).tx;
+        mock.assert_request("eth_call", (call, "latest")).unwrap();
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getLogs", [filter]).unwrap();
+    }
+}
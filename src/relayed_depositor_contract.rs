@@ -0,0 +1,11 @@
+//! Bindings for the relayed withdrawal extension of the `RelayedDepositor` contract. The rest of
+//! its interface is the same as the `Depositor`'s, so [`crate::depositor_contract`] is used for it.
+
+ethers::contract::abigen!(
+    RelayedDepositor,
+    r#"[
+        function WITHDRAW_TYPEHASH() external view returns (bytes32)
+        function withdrawWithSignature(uint256 secret_, uint256 fee_, uint256 deadline_, bytes signature_) external
+        event RelayedWithdrawn(address indexed relayer, uint256 fee, bytes32 secretHash)
+    ]"#
+);
@@ -0,0 +1,360 @@
+//! Relayed withdrawals, so the recipient of a deposit doesn't need ETH to pay for gas.
+//!
+//! The recipient signs the EIP-712 `Withdraw` message of the `RelayedDepositor` contract with the
+//! secret, the relayer's address and fee, and a deadline. The relayer submits it with
+//! `withdrawWithSignature` and receives the fee out of the deposit.
+//!
+//! The relayer is a minimal JSON-RPC service over HTTP with two methods:
+//! - `relayer_info` returns the relayer's address and fee;
+//! - `relayer_withdraw` checks and submits the signed withdrawal and returns the transaction hash.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bdk::bitcoin::secp256k1::SecretKey;
+use ethers::abi::{self, Token};
+use ethers::prelude::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider, Ws};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip712::{EIP712Domain, Eip712};
+use ethers::types::{Address as EthereumAddress, Bytes, TxHash, U256};
+use ethers::utils::keccak256;
+use ethers::utils::Units::Gwei;
+use eyre::{eyre, Context, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+
+use crate::config::Config;
+use crate::gas::GasConfig;
+use crate::keystore;
+use crate::relayed_depositor_contract::RelayedDepositor;
+
+/// EIP-712 domain name and version of the `RelayedDepositor` contract.
+const EIP712_NAME: &str = "RelayedDepositor";
+const EIP712_VERSION: &str = "1";
+
+/// EIP-712 type of the `Withdraw` message.
+const WITHDRAW_TYPE: &str = "Withdraw(uint256 secret,address relayer,uint256 fee,uint256 deadline)";
+
+/// The relayer the participant's withdrawals are sent through.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RelayerClientConfig {
+    /// URL of the relayer's JSON-RPC service.
+    pub url: String,
+    /// The highest relayer's fee in gwei the participant agrees to pay.
+    pub max_fee_gwei: u64,
+}
+
+/// The relayer service run with the `relayer` command.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RelayerConfig {
+    pub listen_address: SocketAddr,
+    /// The fee in gwei the relayer takes out of every deposit it withdraws.
+    pub fee_gwei: u64,
+    /// The key of the relayer's account that pays for gas, either in plaintext or in an Ethereum
+    /// JSON keystore.
+    #[serde(default)]
+    pub private_key: Option<SecretKey>,
+    #[serde(default)]
+    pub keystore: Option<PathBuf>,
+    #[serde(default)]
+    pub keystore_passphrase_env: Option<String>,
+}
+
+impl RelayerConfig {
+    fn key(&self) -> Result<SecretKey> {
+        match (&self.private_key, &self.keystore) {
+            (Some(key), None) => Ok(*key),
+            (None, Some(path)) => keystore::decrypt_ethereum_key(
+                path,
+                &keystore::read_passphrase(
+                    self.keystore_passphrase_env.as_deref(),
+                    "Relayer's keystore passphrase: ",
+                )?,
+            ),
+            _ => Err(eyre!(
+                "either relayer's private_key or keystore must be set"
+            )),
+        }
+    }
+}
+
+/// The `Withdraw` message signed by the recipient of the deposit.
+#[derive(Debug, Clone)]
+pub struct RelayedWithdraw {
+    pub secret: U256,
+    pub relayer: EthereumAddress,
+    pub fee: U256,
+    pub deadline: U256,
+    pub chain_id: u64,
+    pub contract: EthereumAddress,
+}
+
+impl Eip712 for RelayedWithdraw {
+    type Error = Infallible;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(EIP712Domain {
+            name: Some(EIP712_NAME.to_string()),
+            version: Some(EIP712_VERSION.to_string()),
+            chain_id: Some(self.chain_id.into()),
+            verifying_contract: Some(self.contract),
+            salt: None,
+        })
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(WITHDRAW_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(abi::encode(&[
+            Token::FixedBytes(Self::type_hash()?.to_vec()),
+            Token::Uint(self.secret),
+            Token::Address(self.relayer),
+            Token::Uint(self.fee),
+            Token::Uint(self.deadline),
+        ])))
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayerInfo {
+    pub address: EthereumAddress,
+    pub fee: U256,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayRequest {
+    pub secret: U256,
+    pub fee: U256,
+    pub deadline: U256,
+    pub signature: Bytes,
+}
+
+/// Client of the relayer's JSON-RPC service.
+pub struct RelayerClient {
+    provider: Provider<Http>,
+}
+
+impl RelayerClient {
+    pub fn new(url: &str) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(url).wrap_err("invalid relayer URL")?;
+
+        Ok(Self { provider })
+    }
+
+    pub async fn info(&self) -> Result<RelayerInfo> {
+        self.provider
+            .request("relayer_info", ())
+            .await
+            .wrap_err("failed to get relayer info")
+    }
+
+    pub async fn withdraw(&self, request: RelayRequest) -> Result<TxHash> {
+        self.provider
+            .request("relayer_withdraw", [request])
+            .await
+            .wrap_err("relayer has failed to withdraw")
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct JsonRpcRequest {
+    id: serde_json::Value,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+struct Relayer {
+    contract: RelayedDepositor<SignerMiddleware<Provider<Ws>, LocalWallet>>,
+    fee: U256,
+    gas: GasConfig,
+}
+
+impl Relayer {
+    async fn handle(&self, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let response = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => match serde_json::from_slice::<JsonRpcRequest>(&body) {
+                Ok(request) => match self.dispatch(&request.method, request.params).await {
+                    Ok(result) => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request.id,
+                        "result": result,
+                    }),
+                    Err(err) => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request.id,
+                        "error": { "code": -32000, "message": format!("{err:#}") },
+                    }),
+                },
+                Err(err) => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": err.to_string() },
+                }),
+            },
+            Err(err) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": err.to_string() },
+            }),
+        };
+
+        Ok(Response::new(Body::from(response.to_string())))
+    }
+
+    async fn dispatch(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        match method {
+            "relayer_info" => Ok(serde_json::to_value(RelayerInfo {
+                address: self.contract.client_ref().address(),
+                fee: self.fee,
+            })?),
+            "relayer_withdraw" => {
+                let [request]: [RelayRequest; 1] =
+                    serde_json::from_value(params).wrap_err("invalid relay request")?;
+                let tx_hash = self.withdraw(request).await?;
+
+                Ok(serde_json::to_value(tx_hash)?)
+            }
+            _ => Err(eyre!("unknown method {method}")),
+        }
+    }
+
+    async fn withdraw(&self, request: RelayRequest) -> Result<TxHash> {
+        if request.fee < self.fee {
+            return Err(eyre!("fee is less than {} wei", self.fee));
+        }
+
+        let contract_call = self.contract.withdraw_with_signature(
+            request.secret,
+            request.fee,
+            request.deadline,
+            request.signature,
+        );
+
+        // Don't pay for the withdrawals that revert.
+        contract_call
+            .call()
+            .await
+            .wrap_err("withdrawal would revert")?;
+
+        let tx_hash = self
+            .gas
+            .send_transaction(self.contract.client_ref(), contract_call.tx)
+            .await?;
+        println!("| Relayed withdrawal: {tx_hash:?}");
+
+        Ok(tx_hash)
+    }
+}
+
+/// Runs the relayer service until it fails.
+pub async fn serve(config: &Config) -> Result<()> {
+    let relayer_config = config
+        .relayer
+        .as_ref()
+        .ok_or_else(|| eyre!("relayer section must be set in the config"))?;
+
+    let ethereum_client = config
+        .ethereum_client()
+        .await
+        .wrap_err("failed to initialize Ethereum RPC client")?;
+    let chain_id = ethereum_client.get_chainid().await?;
+    let wallet = LocalWallet::from_bytes(&relayer_config.key()?.secret_bytes())?
+        .with_chain_id(chain_id.as_u64());
+    println!("Relayer address: {:?}", wallet.address());
+
+    let relayer = Arc::new(Relayer {
+        contract: RelayedDepositor::new(
+            config.atomic_swap_contract_address,
+            Arc::new(SignerMiddleware::new(ethereum_client, wallet)),
+        ),
+        fee: U256::from(relayer_config.fee_gwei) * U256::exp10(Gwei.as_num() as usize),
+        gas: config.gas.clone(),
+    });
+
+    let service = make_service_fn(move |_| {
+        let relayer = relayer.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let relayer = relayer.clone();
+                async move { relayer.handle(request).await }
+            }))
+        }
+    });
+
+    println!("Relayer is listening on {}", relayer_config.listen_address);
+    Server::bind(&relayer_config.listen_address)
+        .serve(service)
+        .await
+        .wrap_err("relayer service has failed")
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::transaction::eip712::{Eip712, TypedData};
+    use ethers::types::{Address, U256};
+
+    use super::RelayedWithdraw;
+
+    #[tokio::test]
+    async fn test_relayed_withdraw_eip712() {
+        let withdraw = RelayedWithdraw {
+            secret: U256::from(42),
+            relayer: Address::repeat_byte(0x11),
+            fee: U256::exp10(16),
+            deadline: U256::from(1_700_000_000),
+            chain_id: 31337,
+            contract: Address::repeat_byte(0x22),
+        };
+
+        let typed_data: TypedData = serde_json::from_value(serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" },
+                ],
+                "Withdraw": [
+                    { "name": "secret", "type": "uint256" },
+                    { "name": "relayer", "type": "address" },
+                    { "name": "fee", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" },
+                ],
+            },
+            "primaryType": "Withdraw",
+            "domain": {
+                "name": "RelayedDepositor",
+                "version": "1",
+                "chainId": 31337,
+                "verifyingContract": format!("{:?}", withdraw.contract),
+            },
+            "message": {
+                "secret": "42",
+                "relayer": format!("{:?}", withdraw.relayer),
+                "fee": "10000000000000000",
+                "deadline": "1700000000",
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            withdraw.encode_eip712().unwrap(),
+            typed_data.encode_eip712().unwrap()
+        );
+
+        let wallet = LocalWallet::from_bytes(&[0x42; 32]).unwrap();
+        let signature = wallet.sign_typed_data(&withdraw).await.unwrap();
+        signature
+            .verify(withdraw.encode_eip712().unwrap(), wallet.address())
+            .unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+//! Canonical encoding of the swap secret `k`.
+//!
+//! The same `k` lives in three domains:
+//! - secp256k1, as the scalar of the swap key `K = k * G`, so it must be in `[1, n)`;
+//! - the atomic-swap circuit, as four 64-bit limbs, the least significant one first, each of them
+//!   being a BN254 scalar;
+//! - `Depositor`, as the `uint256` passed to `withdraw` that is split into the same limbs before
+//!   hashing with `PoseidonUnit4L`.
+//!
+//! The canonical encoding is the 32-byte big-endian `k`, and every conversion goes through it. The
+//! secp256k1 order is less than `2^256` and a 64-bit limb is always less than the BN254 modulus, so
+//! any `k` in `[1, n)` is represented identically in all three domains, while any other value is
+//! rejected. The Poseidon hash of `k` is a BN254 scalar, so it always fits `bytes32` as is.
+
+use bdk::bitcoin::secp256k1::{self, constants::CURVE_ORDER, rand::Rng};
+use ethers::types::U256;
+use eyre::{eyre, Result};
+
+use crate::poseidon;
+
+/// Number of the 64-bit limbs of the secret in the circuit and the contract.
+const SECRET_LIMBS: usize = 4;
+
+/// The swap secret `k` that is valid in secp256k1, in the circuit and in the contract.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SwapSecret([u8; 32]);
+
+impl SwapSecret {
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self(secp256k1::SecretKey::new(rng).secret_bytes())
+    }
+
+    /// Parses the big-endian secret rejecting zero and the values not less than the secp256k1
+    /// order.
+    pub fn from_bytes(bytes: [u8; 32]) -> Result<Self> {
+        if bytes == [0u8; 32] {
+            return Err(eyre!("swap secret is zero"));
+        }
+
+        // Both are big-endian, so the lexicographical order is the numerical one.
+        if bytes >= CURVE_ORDER {
+            return Err(eyre!("swap secret is not less than the secp256k1 order"));
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Parses the secret from the circuit's limbs, the least significant one first.
+    pub fn from_limbs(limbs: [u64; SECRET_LIMBS]) -> Result<Self> {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.rchunks_exact_mut(8).zip(limbs) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+
+        Self::from_bytes(bytes)
+    }
+
+    /// Parses the secret from the contract's `uint256`, e.g. from the `Withdrawn` event.
+    pub fn from_u256(value: U256) -> Result<Self> {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+
+        Self::from_bytes(bytes)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// Returns the limbs of the secret in the order the circuit and `Depositor._getSecretHash`
+    /// use, the least significant one first.
+    pub fn to_limbs(&self) -> [u64; SECRET_LIMBS] {
+        let mut limbs = [0u64; SECRET_LIMBS];
+        for (limb, chunk) in limbs.iter_mut().zip(self.0.rchunks_exact(8)) {
+            *limb = u64::from_be_bytes(chunk.try_into().expect("chunk is always 8 bytes"));
+        }
+
+        limbs
+    }
+
+    /// Returns the secret as the contract's `withdraw` argument.
+    pub fn to_u256(&self) -> U256 {
+        U256::from_big_endian(&self.0)
+    }
+
+    pub fn secret_key(&self) -> secp256k1::SecretKey {
+        secp256k1::SecretKey::from_slice(&self.0).expect("swap secret is always a valid secret key")
+    }
+
+    /// Returns the Poseidon hash of the secret the circuit outputs and the contract stores.
+    pub fn hash(&self) -> [u8; 32] {
+        poseidon::secret_hash(&self.0)
+    }
+}
+
+impl From<secp256k1::SecretKey> for SwapSecret {
+    fn from(secret_key: secp256k1::SecretKey) -> Self {
+        Self(secret_key.secret_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::bitcoin::secp256k1::{constants::CURVE_ORDER, SecretKey};
+    use ethers::types::U256;
+    use num::{bigint::Sign, BigInt};
+    use proptest::prelude::*;
+
+    use super::SwapSecret;
+    use crate::{poseidon, u256_to_u64array, u64array_to_u256};
+
+    /// Splits the secret into limbs as `Depositor._getSecretHash` does.
+    fn contract_limbs(secret: U256) -> [u64; 4] {
+        let mask = U256::from(u64::MAX);
+        let part1 = secret >> 192;
+        let part2 = (secret >> 128) & mask;
+        let part3 = (secret >> 64) & mask;
+        let part4 = secret & mask;
+
+        [part4, part3, part2, part1].map(|part| part.as_u64())
+    }
+
+    fn curve_order_offset(offset: i64) -> [u8; 32] {
+        let order = BigInt::from_bytes_be(Sign::Plus, &CURVE_ORDER) + offset;
+        let (_, bytes) = order.to_bytes_be();
+
+        let mut result = [0u8; 32];
+        result[32 - bytes.len()..].copy_from_slice(&bytes);
+        result
+    }
+
+    proptest! {
+        #[test]
+        fn test_secret_domains(bytes in any::<[u8; 32]>()) {
+            let is_secret_key = SecretKey::from_slice(&bytes).is_ok();
+            let Ok(secret) = SwapSecret::from_bytes(bytes) else {
+                prop_assert!(!is_secret_key);
+                return Ok(());
+            };
+            prop_assert!(is_secret_key);
+
+            // secp256k1
+            prop_assert_eq!(secret.secret_key().secret_bytes(), bytes);
+
+            // circuit
+            let limbs = secret.to_limbs();
+            let bigint = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            prop_assert_eq!(Some(limbs), u256_to_u64array(bigint.clone()));
+            prop_assert_eq!(u64array_to_u256(limbs), bigint);
+            prop_assert!(SwapSecret::from_limbs(limbs).unwrap() == secret);
+
+            // contract
+            let u256 = secret.to_u256();
+            prop_assert_eq!(contract_limbs(u256), limbs);
+            prop_assert!(SwapSecret::from_u256(u256).unwrap() == secret);
+
+            prop_assert_eq!(secret.hash(), poseidon::secret_hash(&bytes));
+        }
+
+        #[test]
+        fn test_secret_limbs_domains(limbs in any::<[u64; 4]>()) {
+            let bytes = {
+                let (_, bytes) = u64array_to_u256(limbs).to_bytes_be();
+                let mut result = [0u8; 32];
+                result[32 - bytes.len()..].copy_from_slice(&bytes);
+                result
+            };
+
+            match SwapSecret::from_limbs(limbs) {
+                Ok(secret) => {
+                    prop_assert!(SecretKey::from_slice(&bytes).is_ok());
+                    prop_assert_eq!(secret.to_bytes(), bytes);
+                    prop_assert_eq!(secret.to_limbs(), limbs);
+                }
+                Err(_) => prop_assert!(SecretKey::from_slice(&bytes).is_err()),
+            }
+        }
+
+        #[test]
+        fn test_secret_near_curve_order(offset in -1024i64..1024) {
+            let bytes = curve_order_offset(offset);
+
+            let secret = SwapSecret::from_bytes(bytes);
+            prop_assert_eq!(secret.is_ok(), offset < 0);
+            prop_assert_eq!(secret.is_ok(), SecretKey::from_slice(&bytes).is_ok());
+            prop_assert_eq!(SwapSecret::from_u256(U256::from_big_endian(&bytes)).is_ok(), offset < 0);
+        }
+    }
+
+    #[test]
+    fn test_secret_range_bounds() {
+        assert!(SwapSecret::from_bytes([0u8; 32]).is_err());
+        assert!(SwapSecret::from_bytes([0xff; 32]).is_err());
+        assert!(SwapSecret::from_bytes(CURVE_ORDER).is_err());
+        assert!(SwapSecret::from_bytes(curve_order_offset(1)).is_err());
+        assert!(SwapSecret::from_u256(U256::zero()).is_err());
+        assert!(SwapSecret::from_u256(U256::MAX).is_err());
+
+        let max_secret = SwapSecret::from_bytes(curve_order_offset(-1)).unwrap();
+        assert_eq!(
+            max_secret.secret_key().secret_bytes(),
+            curve_order_offset(-1)
+        );
+
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        let one = SwapSecret::from_bytes(one).unwrap();
+        assert_eq!(one.to_limbs(), [1, 0, 0, 0]);
+        assert_eq!(one.to_u256(), U256::one());
+    }
+}
@@ -0,0 +1,308 @@
+//! Signers of the funding wallets' transactions.
+//!
+//! Both the wallets are signed in-process by default. With the PSBT signer, the Bitcoin keys can
+//! live on a hardware or air-gapped signer: the wallet is configured with public descriptors, the
+//! unsigned PSBT with the BIP32 and taproot fields is exported, and the signed one is imported
+//! back to be finalized and broadcast. The wallet isn't locked while the signed PSBT is awaited.
+//! The Ethereum transactions can be signed by a Web3Signer-compatible remote signer, see
+//! [`crate::web3signer`].
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io};
+
+use async_trait::async_trait;
+use bdk::bitcoin::consensus::encode;
+use bdk::bitcoin::secp256k1::SecretKey;
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bdk::database::MemoryDatabase;
+use bdk::{SignOptions, Wallet};
+use ethers::providers::{Http, Provider};
+use ethers::signers::{LocalWallet, Signer, WalletError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address as EthereumAddress, Signature};
+use eyre::{eyre, Context, Result};
+use tokio_util::sync::CancellationToken;
+
+use crate::bitcoin_backend::{self, cancellable};
+use crate::web3signer::{Web3Signer, Web3SignerError};
+
+/// Magic bytes of the binary PSBT encoding.
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+/// Delay between the checks if the signed PSBT file has appeared.
+const SIGNED_PSBT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time to wait for the signed PSBT before giving up on the transaction.
+const SIGNED_PSBT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum BitcoinSigner {
+    /// Signs with the wallet's private keys.
+    #[default]
+    InProcess,
+    /// Exports the unsigned PSBT and waits for the signed one.
+    Psbt {
+        /// The file to write the base64-encoded unsigned PSBT to, stdout if it's not set.
+        #[serde(default)]
+        output: Option<PathBuf>,
+        /// The file to read the signed PSBT from once it appears, either base64-encoded or
+        /// binary, stdin if it's not set.
+        #[serde(default)]
+        input: Option<PathBuf>,
+    },
+}
+
+impl BitcoinSigner {
+    /// Signs the `psbt` built by the `wallet` with the wallet's keys if the signer is in-process.
+    /// The PSBT signer leaves it to [`Self::sign_externally`].
+    pub fn sign_in_process(&self, wallet: &Wallet<MemoryDatabase>, psbt: &mut Psbt) -> Result<()> {
+        if let Self::InProcess = self {
+            wallet.sign(psbt, SignOptions::default())?;
+        }
+
+        Ok(())
+    }
+
+    /// Exports the unsigned `psbt` and combines it with the signed one once it's imported, if the
+    /// signer is the PSBT one. It waits without the wallet, until the signed PSBT appears, the
+    /// timeout or the `cancellation`.
+    pub async fn sign_externally(
+        &self,
+        psbt: &mut Psbt,
+        cancellation: &CancellationToken,
+    ) -> Result<()> {
+        let Self::Psbt { output, input } = self else {
+            return Ok(());
+        };
+
+        if let Some(input) = input.as_deref().filter(|input| input.exists()) {
+            return Err(eyre!(
+                "{} already exists, remove the stale signed PSBT",
+                input.display()
+            ));
+        }
+
+        export_psbt(psbt, output.as_deref())?;
+        let signed_psbt = tokio::time::timeout(
+            SIGNED_PSBT_TIMEOUT,
+            cancellable(cancellation, import_psbt(input.clone())),
+        )
+        .await
+        .map_err(|_| eyre!("signed PSBT hasn't been imported in {SIGNED_PSBT_TIMEOUT:?}"))??;
+
+        if signed_psbt.unsigned_tx.txid() != psbt.unsigned_tx.txid() {
+            return Err(eyre!(
+                "signed PSBT is for another transaction: {}, expected {}",
+                signed_psbt.unsigned_tx.txid(),
+                psbt.unsigned_tx.txid()
+            ));
+        }
+
+        psbt.combine(signed_psbt)
+            .wrap_err("failed to combine signed PSBT")
+    }
+}
+
+/// Finalizes the signed `psbt` with the `wallet` that has built it. The PSBT signed in-process is
+/// finalized already.
+pub fn finalize_psbt(wallet: &Wallet<MemoryDatabase>, psbt: &mut Psbt) -> Result<()> {
+    let is_finalized = psbt
+        .inputs
+        .iter()
+        .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some())
+        || wallet.finalize_psbt(psbt, SignOptions::default())?;
+
+    if !is_finalized {
+        return Err(eyre!("failed to sign and finalize a transaction"));
+    }
+
+    Ok(())
+}
+
+/// Configuration of the signer of the Ethereum transactions.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum EthereumSignerConfig {
+    /// Signs with the funding key from the config.
+    #[default]
+    #[serde(rename = "local")]
+    Local,
+    /// Signs with the `address` key of the Web3Signer-compatible JSON-RPC signer at `url`.
+    #[serde(rename = "web3signer")]
+    Web3Signer {
+        url: String,
+        address: EthereumAddress,
+    },
+}
+
+impl EthereumSignerConfig {
+    pub fn signer(&self, funding_key: Option<SecretKey>, chain_id: u64) -> Result<EthereumSigner> {
+        let signer = match self {
+            Self::Local => {
+                let funding_key = funding_key
+                    .ok_or_else(|| eyre!("Ethereum key must be set for the local signer"))?;
+
+                EthereumSigner::Local(LocalWallet::from_bytes(&funding_key.secret_bytes())?)
+            }
+            Self::Web3Signer { url, address } => {
+                let provider = Provider::<Http>::try_from(url.as_str())
+                    .wrap_err("invalid remote signer URL")?;
+
+                EthereumSigner::Web3Signer(Web3Signer::new(provider, *address))
+            }
+        };
+
+        Ok(signer.with_chain_id(chain_id))
+    }
+}
+
+/// The Ethereum signer chosen in the config.
+#[derive(Debug, Clone)]
+pub enum EthereumSigner {
+    Local(LocalWallet),
+    Web3Signer(Web3Signer),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EthereumSignerError {
+    #[error(transparent)]
+    Local(#[from] WalletError),
+    #[error(transparent)]
+    Web3Signer(#[from] Web3SignerError),
+}
+
+#[async_trait]
+impl Signer for EthereumSigner {
+    type Error = EthereumSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(signer) => Ok(signer.sign_message(message).await?),
+            Self::Web3Signer(signer) => Ok(signer.sign_message(message).await?),
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(signer) => Ok(signer.sign_transaction(tx).await?),
+            Self::Web3Signer(signer) => Ok(signer.sign_transaction(tx).await?),
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(signer) => Ok(signer.sign_typed_data(payload).await?),
+            Self::Web3Signer(signer) => Ok(signer.sign_typed_data(payload).await?),
+        }
+    }
+
+    fn address(&self) -> EthereumAddress {
+        match self {
+            Self::Local(signer) => signer.address(),
+            Self::Web3Signer(signer) => signer.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            Self::Local(signer) => signer.chain_id(),
+            Self::Web3Signer(signer) => signer.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            Self::Local(signer) => Self::Local(signer.with_chain_id(chain_id)),
+            Self::Web3Signer(signer) => Self::Web3Signer(signer.with_chain_id(chain_id)),
+        }
+    }
+}
+
+fn export_psbt(psbt: &Psbt, output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(path) => {
+            fs::write(path, psbt.to_string())
+                .wrap_err_with(|| format!("failed to write PSBT to {}", path.display()))?;
+            println!("| Unsigned PSBT has been written to {}", path.display());
+        }
+        None => println!("| Unsigned PSBT:\n{psbt}"),
+    }
+
+    Ok(())
+}
+
+/// Waits for the signed PSBT file to appear, or reads the PSBT from stdin on the blocking pool.
+async fn import_psbt(input: Option<PathBuf>) -> Result<Psbt> {
+    let bytes = match input {
+        Some(path) => {
+            println!("| Waiting for signed PSBT at {}...", path.display());
+            while !path.exists() {
+                tokio::time::sleep(SIGNED_PSBT_POLL_INTERVAL).await;
+            }
+
+            fs::read(&path)
+                .wrap_err_with(|| format!("failed to read signed PSBT from {}", path.display()))?
+        }
+        None => {
+            println!("| Paste the signed base64-encoded PSBT:");
+            bitcoin_backend::spawn_blocking(|| {
+                let mut line = String::new();
+                io::stdin()
+                    .read_line(&mut line)
+                    .wrap_err("failed to read signed PSBT")?;
+
+                Ok(line.into_bytes())
+            })
+            .await?
+        }
+    };
+
+    parse_psbt(&bytes)
+}
+
+/// Parses either the binary or the base64-encoded PSBT.
+pub(crate) fn parse_psbt(bytes: &[u8]) -> Result<Psbt> {
+    if bytes.starts_with(PSBT_MAGIC) {
+        return encode::deserialize(bytes).wrap_err("failed to parse binary PSBT");
+    }
+
+    std::str::from_utf8(bytes)
+        .wrap_err("PSBT is neither binary nor base64-encoded")?
+        .trim()
+        .parse()
+        .wrap_err("failed to parse base64-encoded PSBT")
+}
+
+#[cfg(test)]
+mod test {
+    use bdk::bitcoin::consensus::encode;
+    use bdk::bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+    use bdk::bitcoin::{PackedLockTime, Transaction};
+
+    use super::parse_psbt;
+
+    #[test]
+    fn test_parse_psbt() {
+        let psbt = Psbt::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap();
+
+        assert_eq!(parse_psbt(psbt.to_string().as_bytes()).unwrap(), psbt);
+        assert_eq!(parse_psbt(format!("{psbt}\n").as_bytes()).unwrap(), psbt);
+        assert_eq!(parse_psbt(&encode::serialize(&psbt)).unwrap(), psbt);
+        assert!(parse_psbt(b"not a psbt").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! The participant's swap store: a JSON file with the swaps it has taken part in.
+//!
+//! Every swap gets its own derivation index for the per-swap keys, see [`crate::hd`]. The index is
+//! reserved in the store before the keys are used, so it's never reused even if the swap fails.
+//! The swap's secret hash and the public keys are recorded once it's started or accepted, so the
+//! counterparty's swap interrupted by a restart can be resumed and either side can refund it, and so is the block its
+//! deposit is sent after, where the withdraw revealing the secret is searched for from.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use eyre::{eyre, Context, Result};
+
+/// The participant's side of the swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SwapRole {
+    /// Funds the swap on Bitcoin and withdraws the deposit.
+    Initiator,
+    /// Deposits the ether and redeems the satoshis.
+    Counterparty,
+}
+
+/// A swap recorded in the store.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapRecord {
+    /// Derivation index of the per-swap keys.
+    pub index: u32,
+    /// The participant's side. It's known once the swap is started or accepted, as the rest of
+    /// the optional fields.
+    #[serde(default)]
+    pub role: Option<SwapRole>,
+    /// Hex-encoded Poseidon hash of the swap secret.
+    #[serde(default)]
+    pub secret_hash: Option<String>,
+    /// Hex-encoded compressed Bitcoin public key of the counterparty.
+    #[serde(default)]
+    pub counterparty_bitcoin_pubkey: Option<String>,
+    /// Hex-encoded compressed swap public key, that is a part of the escrow key of the
+    /// taprootized atomic-swap output.
+    #[serde(default)]
+    pub swap_pubkey: Option<String>,
+    /// The latest Ethereum block before the deposit is sent, where the search for the withdraw
+    /// revealing the secret starts.
+    #[serde(default)]
+    pub deposit_block: Option<u64>,
+    /// Whether the swap is over: the participant has redeemed the counterparty's funds and the
+    /// secret is revealed on-chain, or it has refunded its own.
+    #[serde(default)]
+    pub completed: bool,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapStoreState {
+    next_index: u32,
+    swaps: Vec<SwapRecord>,
+    /// The first blocks whose Ethereum events haven't been handled yet by the event listeners,
+    /// see [`crate::events`].
+    #[serde(default)]
+    ethereum_checkpoints: BTreeMap<String, u64>,
+}
+
+/// The swap store backed by a JSON file that is rewritten on every change.
+#[derive(Debug)]
+pub struct SwapStore {
+    path: PathBuf,
+    state: SwapStoreState,
+}
+
+impl SwapStore {
+    /// Opens the store, or creates an empty one if the file doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let state = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .wrap_err_with(|| format!("failed to parse swap store {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => SwapStoreState::default(),
+            Err(err) => {
+                return Err(err)
+                    .wrap_err_with(|| format!("failed to open swap store {}", path.display()))
+            }
+        };
+
+        Ok(Self { path, state })
+    }
+
+    /// Reserves the next derivation index and saves the store.
+    pub fn next_swap(&mut self) -> Result<u32> {
+        let index = self.state.next_index;
+        self.state.next_index = index
+            .checked_add(1)
+            .ok_or_else(|| eyre!("swap derivation indexes are exhausted"))?;
+        self.state.swaps.push(SwapRecord {
+            index,
+            role: None,
+            secret_hash: None,
+            counterparty_bitcoin_pubkey: None,
+            swap_pubkey: None,
+            deposit_block: None,
+            completed: false,
+        });
+
+        self.save()?;
+
+        Ok(index)
+    }
+
+    /// Records the swap with the derivation `index` once it's started or accepted.
+    pub fn set_started(
+        &mut self,
+        index: u32,
+        role: SwapRole,
+        secret_hash: [u8; 32],
+        counterparty_bitcoin_pubkey: [u8; 33],
+        swap_pubkey: [u8; 33],
+    ) -> Result<()> {
+        let swap = self.swap_mut(index)?;
+        swap.role = Some(role);
+        swap.secret_hash = Some(hex::encode(secret_hash));
+        swap.counterparty_bitcoin_pubkey = Some(hex::encode(counterparty_bitcoin_pubkey));
+        swap.swap_pubkey = Some(hex::encode(swap_pubkey));
+
+        self.save()
+    }
+
+    /// Records the Ethereum block that the deposit of the swap with the derivation `index` is
+    /// sent after.
+    pub fn set_deposit_block(&mut self, index: u32, block: u64) -> Result<()> {
+        self.swap_mut(index)?.deposit_block = Some(block);
+
+        self.save()
+    }
+
+    /// Marks the swap with the derivation `index` completed.
+    pub fn set_completed(&mut self, index: u32) -> Result<()> {
+        self.swap_mut(index)?.completed = true;
+
+        self.save()
+    }
+
+    /// Returns the last started swap on the `role` side that isn't completed.
+    pub fn in_flight_swap(&self, role: SwapRole) -> Option<&SwapRecord> {
+        self.state
+            .swaps
+            .iter()
+            .rev()
+            .find(|swap| swap.role == Some(role) && !swap.completed)
+    }
+
+    fn swap_mut(&mut self, index: u32) -> Result<&mut SwapRecord> {
+        self.state
+            .swaps
+            .iter_mut()
+            .find(|swap| swap.index == index)
+            .ok_or_else(|| eyre!("swap with derivation index {index} is not in the store"))
+    }
+
+    /// Returns the checkpoint block of the event listener.
+    pub fn ethereum_checkpoint(&self, listener: &str) -> Option<u64> {
+        self.state.ethereum_checkpoints.get(listener).copied()
+    }
+
+    /// Records the checkpoint block of the event listener.
+    pub fn set_ethereum_checkpoint(&mut self, listener: &str, block: u64) -> Result<()> {
+        self.state
+            .ethereum_checkpoints
+            .insert(listener.to_string(), block);
+
+        self.save()
+    }
+
+    /// Writes the store to a temporary file and renames it, so a crash never leaves a partially
+    /// written store.
+    fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let json = serde_json::to_vec_pretty(&self.state)?;
+
+        fs::write(&tmp_path, json)
+            .and_then(|_| fs::rename(&tmp_path, &self.path))
+            .wrap_err_with(|| format!("failed to save swap store {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SwapRole, SwapStore};
+
+    #[test]
+    fn test_swap_store() {
+        let path = std::env::temp_dir().join(format!("{}-swaps.json", std::process::id()));
+
+        let mut store = SwapStore::open(&path).unwrap();
+        assert_eq!(store.next_swap().unwrap(), 0);
+        assert_eq!(store.next_swap().unwrap(), 1);
+        store
+            .set_started(
+                0,
+                SwapRole::Counterparty,
+                [0x11; 32],
+                [0x02; 33],
+                [0x03; 33],
+            )
+            .unwrap();
+        assert!(store
+            .set_started(
+                2,
+                SwapRole::Counterparty,
+                [0x22; 32],
+                [0x02; 33],
+                [0x03; 33]
+            )
+            .is_err());
+        store.set_deposit_block(0, 17).unwrap();
+        store.set_ethereum_checkpoint("withdrawn", 42).unwrap();
+        store.set_completed(1).unwrap();
+
+        let mut store = SwapStore::open(&path).unwrap();
+        assert_eq!(store.state.swaps.len(), 2);
+        assert_eq!(
+            store.state.swaps[0].secret_hash,
+            Some(hex::encode([0x11; 32]))
+        );
+        assert_eq!(
+            store.state.swaps[0].counterparty_bitcoin_pubkey,
+            Some(hex::encode([0x02; 33]))
+        );
+        assert_eq!(
+            store.state.swaps[0].swap_pubkey,
+            Some(hex::encode([0x03; 33]))
+        );
+        assert_eq!(store.state.swaps[0].deposit_block, Some(17));
+        assert_eq!(store.state.swaps[1].secret_hash, None);
+        assert_eq!(
+            store
+                .in_flight_swap(SwapRole::Counterparty)
+                .map(|swap| swap.index),
+            Some(0)
+        );
+        assert_eq!(store.in_flight_swap(SwapRole::Initiator), None);
+        assert!(!store.state.swaps[0].completed);
+        assert!(store.state.swaps[1].completed);
+        assert_eq!(store.ethereum_checkpoint("withdrawn"), Some(42));
+        assert_eq!(store.ethereum_checkpoint("deposited"), None);
+        assert_eq!(store.next_swap().unwrap(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
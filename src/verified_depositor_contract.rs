@@ -0,0 +1,13 @@
+//! Bindings for the `depositWithProof` extension of the `VerifiedDepositor` contract. The rest of
+//! its interface is the same as the `Depositor`'s, so [`crate::depositor_contract`] is used for it.
+#![allow(clippy::too_many_arguments)]
+
+ethers::contract::abigen!(
+    VerifiedDepositor,
+    r#"[
+        struct SwapProof { uint256[2] a; uint256[2][2] b; uint256[2] c; }
+        function isProofRequired() external view returns (bool)
+        function depositWithProof(address recipient_, bytes32 secretHash_, uint256 lockTime_, uint256[8] pubkey_, SwapProof proof_) external payable
+        event SwapProofVerified(bytes32 secretHash, uint256[8] pubkey)
+    ]"#
+);
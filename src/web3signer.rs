@@ -0,0 +1,202 @@
+//! Ethereum signer backed by a Web3Signer-compatible JSON-RPC endpoint, so the key never enters
+//! this process.
+//!
+//! Transactions are signed with `eth_signTransaction` and messages with `eth_sign`. The returned
+//! signatures are checked to be made by the configured address for the requested transaction or
+//! message, so a misbehaving signer can't make us broadcast something else.
+//!
+//! EIP-712 typed data isn't supported: `eth_signTypedData` takes the typed data's JSON, which
+//! [`Eip712`] doesn't provide. So the relayed withdraw requires a local Ethereum swap key, which
+//! [`crate::config::WalletsConfig::validate`] checks at load.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use ethers::providers::{Http, JsonRpcClient, Provider, ProviderError};
+use ethers::signers::Signer;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address, Bytes, Signature, SignatureError, H256};
+use ethers::utils::{hash_message, rlp::Rlp};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Web3SignerError {
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+    #[error("failed to decode signed transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("remote signer has signed another transaction")]
+    TransactionMismatch,
+    #[error("remote signer has signed with another address: {0:?}")]
+    AddressMismatch(Address),
+    #[error("remote signer doesn't support EIP-712 typed data")]
+    TypedDataUnsupported,
+}
+
+#[derive(Debug, Clone)]
+pub struct Web3Signer<P = Http> {
+    provider: Provider<P>,
+    address: Address,
+    chain_id: u64,
+}
+
+impl<P: JsonRpcClient> Web3Signer<P> {
+    /// Creates the signer of the `address` key held by the remote signer behind the `provider`.
+    pub fn new(provider: Provider<P>, address: Address) -> Self {
+        Self {
+            provider,
+            address,
+            chain_id: 1,
+        }
+    }
+
+    fn check_signer(&self, signature: &Signature, hash: H256) -> Result<(), Web3SignerError> {
+        let signer = signature.recover(hash)?;
+        if signer != self.address {
+            return Err(Web3SignerError::AddressMismatch(signer));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> Signer for Web3Signer<P> {
+    type Error = Web3SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        let message = message.as_ref();
+
+        let signature: String = self
+            .provider
+            .request("eth_sign", (self.address, Bytes::from(message.to_vec())))
+            .await?;
+        let signature = Signature::from_str(&signature)?;
+
+        self.check_signer(&signature, hash_message(message))?;
+
+        Ok(signature)
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+
+        let raw_tx: Bytes = self.provider.request("eth_signTransaction", [&tx]).await?;
+        let (signed_tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw_tx))
+            .map_err(|err| Web3SignerError::InvalidTransaction(err.to_string()))?;
+
+        let sighash = tx.sighash();
+        if signed_tx.sighash() != sighash {
+            return Err(Web3SignerError::TransactionMismatch);
+        }
+        self.check_signer(&signature, sighash)?;
+
+        Ok(signature)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        _payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        Err(Web3SignerError::TypedDataUnsupported)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::providers::Provider;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{Address, Bytes, TransactionRequest};
+
+    use super::{Web3Signer, Web3SignerError};
+
+    const CHAIN_ID: u64 = 1337;
+
+    fn transaction(value: u64) -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::repeat_byte(1))
+            .value(value)
+            .nonce(0)
+            .gas(21_000)
+            .gas_price(1)
+            .chain_id(CHAIN_ID)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn test_sign_transaction() {
+        let wallet = LocalWallet::from_bytes(&[0x42; 32])
+            .unwrap()
+            .with_chain_id(CHAIN_ID);
+        let (provider, mock) = Provider::mocked();
+        let signer = Web3Signer::new(provider, wallet.address()).with_chain_id(CHAIN_ID);
+
+        let mut tx = transaction(1000);
+        tx.set_from(wallet.address());
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+
+        mock.push::<Bytes, _>(tx.rlp_signed(&signature)).unwrap();
+        assert_eq!(signer.sign_transaction(&tx).await.unwrap(), signature);
+
+        let mut other_tx = transaction(2000);
+        other_tx.set_from(wallet.address());
+        let other_signature = wallet.sign_transaction(&other_tx).await.unwrap();
+
+        mock.push::<Bytes, _>(other_tx.rlp_signed(&other_signature))
+            .unwrap();
+        assert!(matches!(
+            signer.sign_transaction(&tx).await,
+            Err(Web3SignerError::TransactionMismatch)
+        ));
+
+        let other_wallet = LocalWallet::from_bytes(&[0x43; 32])
+            .unwrap()
+            .with_chain_id(CHAIN_ID);
+        let other_signature = other_wallet.sign_transaction(&tx).await.unwrap();
+
+        mock.push::<Bytes, _>(tx.rlp_signed(&other_signature))
+            .unwrap();
+        assert!(matches!(
+            signer.sign_transaction(&tx).await,
+            Err(Web3SignerError::AddressMismatch(address)) if address == other_wallet.address()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sign_message() {
+        let wallet = LocalWallet::from_bytes(&[0x42; 32]).unwrap();
+        let (provider, mock) = Provider::mocked();
+        let signer = Web3Signer::new(provider, wallet.address());
+
+        let signature = wallet.sign_message("atomic swap").await.unwrap();
+
+        mock.push::<String, _>(signature.to_string()).unwrap();
+        assert_eq!(signer.sign_message("atomic swap").await.unwrap(), signature);
+
+        mock.push::<String, _>(signature.to_string()).unwrap();
+        assert!(signer.sign_message("another message").await.is_err());
+    }
+}